@@ -0,0 +1,87 @@
+//! Hands the access token to plugins over a Unix socket in a private directory, so it never appears
+//! in their arguments or environment. The socket only exists while the plugin runs.
+
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::Result;
+
+pub(crate) const AGENT_SOCKET_VAR_NAME: &str = "BWS_AGENT_SOCKET";
+
+pub(crate) struct Agent {
+    // Removed with the socket when the agent is dropped
+    _dir: tempfile::TempDir,
+    pub(crate) socket: PathBuf,
+}
+
+#[cfg(unix)]
+impl Agent {
+    /// Starts serving the access token to every connection, until the agent is dropped.
+    pub(crate) fn start(access_token: &str) -> Result<Agent> {
+        use std::{io::Write, os::unix::net::UnixListener};
+
+        // Socket paths are limited to 104 bytes on macOS, where the temporary directory is already
+        // about 50, so a short directory in /tmp is used instead
+        let dir = tempfile::Builder::new()
+            .prefix("bws-")
+            .rand_bytes(8)
+            .tempdir_in("/tmp")?;
+        let agent = Agent {
+            socket: dir.path().join("agent.sock"),
+            _dir: dir,
+        };
+
+        let listener = UnixListener::bind(&agent.socket)?;
+        let access_token = access_token.to_string();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.write_all(access_token.as_bytes());
+            }
+        });
+
+        Ok(agent)
+    }
+}
+
+#[cfg(not(unix))]
+impl Agent {
+    pub(crate) fn start(_access_token: &str) -> Result<Agent> {
+        color_eyre::eyre::bail!(crate::error::CliError::new(
+            crate::error::ErrorCategory::Validation,
+            "Plugins are only supported on Unix, where the access token is passed to them over a socket"
+        ))
+    }
+}
+
+/// Reads the access token from an agent started by the `bws` that ran this plugin.
+#[cfg(unix)]
+pub(crate) fn read_token(socket: &Path) -> Result<String> {
+    use std::{io::Read, os::unix::net::UnixStream};
+
+    let mut access_token = String::new();
+    UnixStream::connect(socket)?.read_to_string(&mut access_token)?;
+    Ok(access_token.trim().to_string())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn read_token(_socket: &Path) -> Result<String> {
+    color_eyre::eyre::bail!("The access token agent is only supported on Unix")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_the_token_until_dropped() {
+        let agent = Agent::start("0.token").unwrap();
+        let socket = agent.socket.clone();
+        assert!(socket.as_os_str().len() < 104);
+
+        assert_eq!("0.token", read_token(&socket).unwrap());
+        assert_eq!("0.token", read_token(&socket).unwrap());
+
+        drop(agent);
+        assert!(!socket.exists());
+        assert!(read_token(&socket).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use color_eyre::eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CliError, ErrorCategory};
+
+/// A user-defined subcommand from the `[aliases]` table of the config file, replaced by its
+/// arguments before they're parsed. `$1` to `$9` are replaced by the arguments given to the alias,
+/// `$@` by all of them, and `$$` by a `$`. Arguments no placeholder uses are added at the end.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Alias {
+    /// A command line, split into words like a shell would but without any expansions.
+    Line(String),
+    Args(Vec<String>),
+}
+
+impl std::fmt::Display for Alias {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Alias::Line(line) => write!(f, "{line}"),
+            Alias::Args(args) => write!(f, "{}", args.join(" ")),
+        }
+    }
+}
+
+impl Alias {
+    pub(crate) fn words(&self) -> Result<Vec<String>> {
+        match self {
+            Alias::Line(line) => split_words(line),
+            Alias::Args(args) => Ok(args.clone()),
+        }
+    }
+
+    pub(crate) fn expand(&self, name: &str, args: &[String]) -> Result<Vec<String>> {
+        let mut used = vec![false; args.len()];
+        let mut expanded = Vec::new();
+
+        for word in self.words()? {
+            if word == "$@" {
+                expanded.extend(args.iter().cloned());
+                used.iter_mut().for_each(|u| *u = true);
+                continue;
+            }
+
+            let mut result = String::new();
+            let mut chars = word.chars().peekable();
+            while let Some(c) = chars.next() {
+                if c != '$' {
+                    result.push(c);
+                    continue;
+                }
+
+                match chars.peek().copied() {
+                    Some('$') => {
+                        chars.next();
+                        result.push('$');
+                    }
+                    Some(digit @ '1'..='9') => {
+                        chars.next();
+                        let index = digit as usize - '1' as usize;
+                        let Some(arg) = args.get(index) else {
+                            bail!(CliError::new(
+                                ErrorCategory::Validation,
+                                format!(
+                                    "The alias `{name}` uses ${digit}, so it needs at least {digit} arguments"
+                                )
+                            ));
+                        };
+                        result.push_str(arg);
+                        used[index] = true;
+                    }
+                    _ => result.push('$'),
+                }
+            }
+            expanded.push(result);
+        }
+
+        expanded.extend(
+            args.iter()
+                .zip(used)
+                .filter(|(_, used)| !used)
+                .map(|(arg, _)| arg.clone()),
+        );
+
+        Ok(expanded)
+    }
+}
+
+/// Splits a command line at whitespace outside of quotes. Single quotes keep everything as is,
+/// while a backslash escapes the next character outside of them.
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => bail!(unclosed_quote(line)),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => bail!(unclosed_quote(line)),
+                        },
+                        Some(c) => word.push(c),
+                        None => bail!(unclosed_quote(line)),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.next());
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+fn unclosed_quote(line: &str) -> CliError {
+    CliError::new(
+        ErrorCategory::Validation,
+        format!("Unclosed quote in the alias `{line}`"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_like_a_shell() {
+        assert_eq!(
+            strings(&[
+                "run",
+                "--shell",
+                "bash",
+                "--",
+                "echo $HOME",
+                "it's",
+                "a\"b",
+                ""
+            ]),
+            split_words(r#"run  --shell bash -- 'echo $HOME' "it's" a\"b """#).unwrap()
+        );
+        assert!(split_words("run 'unclosed").is_err());
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let alias = Alias::Line("run --stage $1 -- ./start.sh --port=$2 $$1".to_string());
+
+        assert_eq!(
+            strings(&[
+                "run",
+                "--stage",
+                "prod",
+                "--",
+                "./start.sh",
+                "--port=8080",
+                "$1",
+                "--verbose"
+            ]),
+            alias
+                .expand("start", &strings(&["prod", "8080", "--verbose"]))
+                .unwrap()
+        );
+        assert!(alias.expand("start", &strings(&["prod"])).is_err());
+    }
+
+    #[test]
+    fn appends_arguments_without_placeholders() {
+        let alias = Alias::Args(strings(&["secret", "list"]));
+        assert_eq!(
+            strings(&["secret", "list", "--tag", "db"]),
+            alias.expand("ls", &strings(&["--tag", "db"])).unwrap()
+        );
+
+        let alias = Alias::Line("run -- ./script.sh $@ --done".to_string());
+        assert_eq!(
+            strings(&["run", "--", "./script.sh", "a", "b", "--done"]),
+            alias.expand("script", &strings(&["a", "b"])).unwrap()
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use fs4::FileExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{CliError, ErrorCategory};
+
+/// The previous hash of the first entry in the log.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+static AUDIT_LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// The audit log of the profile in use, and what to record about the current invocation.
+#[derive(Debug)]
+pub(crate) struct AuditLog {
+    pub(crate) path: PathBuf,
+    pub(crate) profile: String,
+    /// The subcommands of the invocation, such as `secret delete`.
+    pub(crate) command: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A project or secret changed by a command, with its name or key. Values are never recorded.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AuditItem {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+}
+
+/// The hashed part of an entry.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditRecord {
+    timestamp: DateTime<Utc>,
+    user: String,
+    hostname: String,
+    profile: String,
+    command: String,
+    action: AuditAction,
+    items: Vec<AuditItem>,
+    prev_hash: String,
+}
+
+/// A line of the log. The hash covers the record and, through `prev_hash`, every entry before it.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditEntry {
+    #[serde(flatten)]
+    record: AuditRecord,
+    hash: String,
+}
+
+impl AuditRecord {
+    fn hash(&self) -> Result<String> {
+        let hash = Sha256::digest(serde_json::to_vec(self)?);
+        Ok(hash.iter().map(|b| format!("{b:02x}")).collect())
+    }
+}
+
+/// Enables recording for the rest of the process. Only the first call has an effect.
+pub(crate) fn init(log: AuditLog) {
+    let _ = AUDIT_LOG.set(log);
+}
+
+/// Appends an entry for a change that was made, if the profile has an audit log. Failing to record
+/// is an error, so a change is never silently missing from the log.
+pub(crate) fn record(
+    action: AuditAction,
+    items: impl IntoIterator<Item = (Uuid, String)>,
+) -> Result<()> {
+    let Some(log) = AUDIT_LOG.get() else {
+        return Ok(());
+    };
+
+    let items: Vec<AuditItem> = items
+        .into_iter()
+        .map(|(id, name)| AuditItem { id, name })
+        .collect();
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    append(log, action, items)
+        .wrap_err_with(|| format!("Failed to write the audit log {}", log.path.display()))
+}
+
+fn append(log: &AuditLog, action: AuditAction, items: Vec<AuditItem>) -> Result<()> {
+    // Held until the file is closed, so concurrent invocations can't chain to the same entry
+    let mut file = open_for_append(&log.path)?;
+    file.lock_exclusive()?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let prev_hash = match last_entry(&contents)? {
+        Some(entry) => entry.hash,
+        None => GENESIS_HASH.to_string(),
+    };
+
+    let record = AuditRecord {
+        timestamp: Utc::now(),
+        user: current_user(),
+        hostname: hostname(),
+        profile: log.profile.clone(),
+        command: log.command.clone(),
+        action,
+        items,
+        prev_hash,
+    };
+    let hash = record.hash()?;
+
+    let mut line = serde_json::to_string(&AuditEntry { record, hash })?;
+    line.push('\n');
+    file.write_all(line.as_bytes())?;
+
+    Ok(())
+}
+
+/// Checks that every entry of the log is unchanged and chained to the one before it, returning the
+/// number of entries and the hash of the last one.
+pub(crate) fn verify(path: &Path) -> Result<(usize, String)> {
+    let contents = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut count = 0;
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let tampered = |reason: &str| {
+            CliError::new(
+                ErrorCategory::Validation,
+                format!("Line {line_number}: {reason}"),
+            )
+        };
+
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(line) else {
+            bail!(tampered("not a valid audit log entry"));
+        };
+        if entry.record.prev_hash != prev_hash {
+            bail!(tampered(
+                "the chain is broken, an entry before it was removed or reordered"
+            ));
+        }
+        if entry.record.hash()? != entry.hash {
+            bail!(tampered("the entry was modified"));
+        }
+
+        prev_hash = entry.hash;
+        count += 1;
+    }
+
+    Ok((count, prev_hash))
+}
+
+fn last_entry(contents: &str) -> Result<Option<AuditEntry>> {
+    let Some(line) = contents.lines().last() else {
+        return Ok(None);
+    };
+
+    match serde_json::from_str(line) {
+        Ok(entry) => Ok(Some(entry)),
+        Err(_) => bail!("The last entry is invalid. Run `bws audit verify` to check the log."),
+    }
+}
+
+#[cfg(unix)]
+fn open_for_append(path: &Path) -> Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    Ok(std::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .mode(0o600)
+        .open(path)?)
+}
+
+#[cfg(not(unix))]
+fn open_for_append(path: &Path) -> Result<std::fs::File> {
+    Ok(std::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?)
+}
+
+fn current_user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_log(path: &Path, entries: usize) {
+        let log = AuditLog {
+            path: path.to_owned(),
+            profile: "prod".to_string(),
+            command: "secret delete".to_string(),
+        };
+        for i in 0..entries {
+            append(
+                &log,
+                AuditAction::Delete,
+                vec![AuditItem {
+                    id: Uuid::new_v4(),
+                    name: format!("SECRET_{i}"),
+                }],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn chain_verifies() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        write_log(&path, 3);
+
+        let (count, last_hash) = verify(&path).unwrap();
+        assert_eq!(3, count);
+        assert_ne!(GENESIS_HASH, last_hash);
+    }
+
+    #[test]
+    fn concurrent_appends_stay_chained() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| write_log(&path, 10));
+            }
+        });
+
+        assert_eq!(40, verify(&path).unwrap().0);
+    }
+
+    #[test]
+    fn modified_entry_is_detected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        write_log(&path, 3);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, contents.replacen("SECRET_1", "SECRET_X", 1)).unwrap();
+
+        let error = verify(&path).unwrap_err().to_string();
+        assert!(error.starts_with("Line 2:"), "{error}");
+    }
+
+    #[test]
+    fn removed_entry_is_detected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        write_log(&path, 3);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+
+        let error = verify(&path).unwrap_err().to_string();
+        assert!(error.starts_with("Line 2:"), "{error}");
+    }
+}
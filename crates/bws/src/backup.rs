@@ -0,0 +1,243 @@
+//! The archive format written by `bws backup`.
+//!
+//! ```text
+//! offset  size  field
+//! 0       6     magic, "BWSBAK"
+//! 6       1     format version, currently 1
+//! 7       4     Argon2id memory cost in KiB, little endian
+//! 11      4     Argon2id iterations, little endian
+//! 15      4     Argon2id parallelism, little endian
+//! 19      16    Argon2id salt
+//! 35      24    XChaCha20-Poly1305 nonce
+//! 59      n     XChaCha20-Poly1305 ciphertext, ending with the 16 byte Poly1305 tag
+//! ```
+//!
+//! Argon2id derives the 32 byte key from the passphrase and the salt. The header, bytes 0 to 35,
+//! is authenticated as associated data, so the key derivation parameters can't be changed without
+//! the archive being rejected. The plaintext is [`BackupData`] as JSON.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use bitwarden::secrets_manager::secrets::SecretResponse;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{bail, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    crypto::{self, KEY_LEN, NONCE_LEN, TAG_LEN},
+    error::{CliError, ErrorCategory},
+};
+
+const MAGIC: &[u8; 6] = b"BWSBAK";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN;
+
+// Archives carry their own parameters, so these bound what we're willing to spend on opening one
+// before its tag can be checked. 1 GiB is far more than the default needs.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 100;
+
+/// Everything in a backup, as it's stored inside the encrypted archive.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BackupData {
+    pub(crate) created: DateTime<Utc>,
+    pub(crate) organization_id: Uuid,
+    pub(crate) projects: Vec<BackupProject>,
+    pub(crate) secrets: Vec<BackupSecret>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BackupProject {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BackupSecret {
+    pub(crate) id: Uuid,
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) note: String,
+}
+
+impl From<SecretResponse> for BackupSecret {
+    fn from(secret: SecretResponse) -> Self {
+        BackupSecret {
+            id: secret.id,
+            project_id: secret.project_id,
+            key: secret.key,
+            value: secret.value,
+            note: secret.note,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct KdfParams {
+    pub(crate) memory_kib: u32,
+    pub(crate) iterations: u32,
+    pub(crate) parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+pub(crate) fn encrypt(data: &BackupData, passphrase: &str, kdf: KdfParams) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt, kdf)?;
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    header.extend_from_slice(&kdf.memory_kib.to_le_bytes());
+    header.extend_from_slice(&kdf.iterations.to_le_bytes());
+    header.extend_from_slice(&kdf.parallelism.to_le_bytes());
+    header.extend_from_slice(&salt);
+
+    crypto::seal(header, &serde_json::to_vec(data)?, &key)
+}
+
+pub(crate) fn decrypt(archive: &[u8], passphrase: &str) -> Result<BackupData> {
+    if archive.len() < HEADER_LEN + NONCE_LEN + TAG_LEN || !archive.starts_with(MAGIC) {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "The file isn't a bws backup archive"
+        ));
+    }
+
+    let version = archive[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!("Unsupported backup format version {version}")
+        ));
+    }
+
+    let kdf = KdfParams {
+        memory_kib: read_u32(archive, 7),
+        iterations: read_u32(archive, 11),
+        parallelism: read_u32(archive, 15),
+    };
+    if kdf.memory_kib > MAX_MEMORY_KIB || kdf.iterations > MAX_ITERATIONS {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "The key derivation parameters of the archive are out of range"
+        ));
+    }
+
+    let key = derive_key(passphrase, &archive[19..HEADER_LEN], kdf)?;
+    let Some(plaintext) = crypto::unseal(archive, HEADER_LEN, &key) else {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "The passphrase is wrong or the archive is corrupted"
+        ));
+    };
+
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<[u8; KEY_LEN]> {
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_LEN),
+    )?;
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+        passphrase.as_bytes(),
+        salt,
+        &mut key,
+    )?;
+
+    Ok(key)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters, the defaults take too long for tests
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn backup_data() -> BackupData {
+        let project_id = Uuid::new_v4();
+        BackupData {
+            created: Utc::now(),
+            organization_id: Uuid::new_v4(),
+            projects: vec![BackupProject {
+                id: project_id,
+                name: "Production".to_string(),
+            }],
+            secrets: vec![BackupSecret {
+                id: Uuid::new_v4(),
+                project_id: Some(project_id),
+                key: "DB_PASSWORD".to_string(),
+                value: "hunter2".to_string(),
+                note: "rotated monthly".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = backup_data();
+        let archive = encrypt(&data, "correct horse", TEST_KDF).unwrap();
+
+        assert!(archive.starts_with(MAGIC));
+        assert_eq!(FORMAT_VERSION, archive[6]);
+        assert_eq!(TEST_KDF.memory_kib, read_u32(&archive, 7));
+        assert!(!String::from_utf8_lossy(&archive).contains("hunter2"));
+
+        assert_eq!(data, decrypt(&archive, "correct horse").unwrap());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let archive = encrypt(&backup_data(), "correct horse", TEST_KDF).unwrap();
+        assert!(decrypt(&archive, "battery staple").is_err());
+    }
+
+    #[test]
+    fn tampering_is_rejected() {
+        let mut archive = encrypt(&backup_data(), "correct horse", TEST_KDF).unwrap();
+
+        // Changing the header is caught by the tag as well
+        archive[20] ^= 1;
+        assert!(decrypt(&archive, "correct horse").is_err());
+        archive[20] ^= 1;
+
+        let last = archive.len() - TAG_LEN - 1;
+        archive[last] ^= 1;
+        assert!(decrypt(&archive, "correct horse").is_err());
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(decrypt(b"not an archive", "correct horse").is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use bitwarden_cli::Color;
-use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 use uuid::Uuid;
 
@@ -9,10 +9,12 @@ pub(crate) const ACCESS_TOKEN_KEY_VAR_NAME: &str = "BWS_ACCESS_TOKEN";
 pub(crate) const CONFIG_FILE_KEY_VAR_NAME: &str = "BWS_CONFIG_FILE";
 pub(crate) const PROFILE_KEY_VAR_NAME: &str = "BWS_PROFILE";
 pub(crate) const SERVER_URL_KEY_VAR_NAME: &str = "BWS_SERVER_URL";
+pub(crate) const ALLOW_INSECURE_STATE_VAR_NAME: &str = "BWS_ALLOW_INSECURE_STATE";
 pub(crate) const UUIDS_AS_KEYNAMES_VAR_NAME: &str = "BWS_UUIDS_AS_KEYNAMES";
 
 pub(crate) const DEFAULT_CONFIG_FILENAME: &str = "config";
 pub(crate) const DEFAULT_CONFIG_DIRECTORY: &str = ".config/bws";
+pub(crate) const LOCAL_CONFIG_FILENAME: &str = ".bws.toml";
 
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -22,6 +24,18 @@ pub(crate) enum ProfileKey {
     server_identity,
     state_dir,
     state_opt_out,
+    access_token_file,
+    access_token_command,
+    output,
+    color,
+    default_project,
+    reveal,
+    ca_cert_path,
+    http_proxy,
+    timeout,
+    protected,
+    journal_retention,
+    audit_log,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -51,6 +65,13 @@ pub(crate) struct Cli {
     #[arg(short = 't', long, global = true, env = ACCESS_TOKEN_KEY_VAR_NAME, hide_env_values = true, help="Specify access token for the service account")]
     pub(crate) access_token: Option<String>,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Read the access token from the first line of stdin, taking precedence over --access-token"
+    )]
+    pub(crate) access_token_stdin: bool,
+
     #[arg(
         short = 'f',
         long,
@@ -65,12 +86,42 @@ pub(crate) struct Cli {
 
     #[arg(short = 'u', long, global = true, env = SERVER_URL_KEY_VAR_NAME, help="Override the server URL from the config file")]
     pub(crate) server_url: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        env = ALLOW_INSECURE_STATE_VAR_NAME,
+        help = "Use state files even if they are accessible by other users"
+    )]
+    pub(crate) allow_insecure_state: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Show secret values in the output, even if the profile sets `reveal` to false"
+    )]
+    pub(crate) reveal: bool,
+
+    #[arg(
+        short = 'y',
+        long,
+        global = true,
+        help = "Don't ask for confirmation before deleting or bulk editing. Protected profiles still ask for their name"
+    )]
+    pub(crate) yes: bool,
 }
 
 #[derive(Subcommand, Debug)]
 pub(crate) enum Commands {
-    #[command(long_about = "Configure the CLI", arg_required_else_help(true))]
+    #[command(
+        long_about = "Configure the CLI",
+        arg_required_else_help(true),
+        args_conflicts_with_subcommands(true)
+    )]
     Config {
+        #[command(subcommand)]
+        cmd: Option<ConfigCommand>,
+
         name: Option<ProfileKey>,
         value: Option<String>,
 
@@ -79,7 +130,40 @@ pub(crate) enum Commands {
     },
 
     #[command(long_about = "Generate shell completion files")]
-    Completions { shell: Option<Shell> },
+    Completions {
+        shell: Option<Shell>,
+
+        #[arg(
+            long,
+            help = "Complete project and secret IDs using the results of the last list commands"
+        )]
+        dynamic: bool,
+    },
+
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        shell: Shell,
+        index: usize,
+        #[arg(last = true, allow_hyphen_values = true)]
+        words: Vec<std::ffi::OsString>,
+    },
+
+    #[command(long_about = "Manage the local state files")]
+    State {
+        #[command(subcommand)]
+        cmd: StateCommand,
+    },
+
+    #[command(
+        long_about = "Show the access token, organization, server and profile in use. The organization is read from the saved session, which may log in to the server when there is none"
+    )]
+    Status {
+        #[arg(
+            long,
+            help = "Log in to the server and report the latency and any failure"
+        )]
+        check: bool,
+    },
 
     #[command(long_about = "Commands available on Projects")]
     Project {
@@ -102,18 +186,289 @@ pub(crate) enum Commands {
             help = "Don't inherit environment variables from the current shell"
         )]
         no_inherit_env: bool,
-        #[arg(long, help = "The ID of the project to use")]
+        #[command(flatten)]
+        selection: SelectionArgs,
+        #[arg(
+            long,
+            conflicts_with = "command",
+            help = "Run every process of this Procfile, with the secrets injected into each"
+        )]
+        procfile: Option<PathBuf>,
+    },
+    #[command(long_about = format!("Check that the secrets listed as required in {} exist", LOCAL_CONFIG_FILENAME))]
+    Check {
+        #[arg(long, help = "The ID of the project to check")]
+        project_id: Option<Uuid>,
+        #[arg(
+            long,
+            conflicts_with = "project_id",
+            help = format!("The stage from {} whose project to check", LOCAL_CONFIG_FILENAME)
+        )]
+        stage: Option<String>,
+    },
+    #[command(
+        long_about = "Compare the secrets of a project with another project or a .env file. Exits with 10 when there are differences"
+    )]
+    Diff {
+        #[arg(help = "The ID of the project to compare")]
+        project_id: Uuid,
+        #[arg(
+            value_name = "PROJECT_ID|FILE",
+            help = "The ID of a project, or the path to a .env file"
+        )]
+        other: String,
+        #[arg(long, help = "Only compare which keys exist, ignoring their values")]
+        keys_only: bool,
+    },
+    #[command(
+        long_about = "Search files for the values of the organization's secrets. Exits with 10 when a value is found"
+    )]
+    #[clap(group = ArgGroup::new("scan_source").required(true).multiple(true))]
+    Scan {
+        #[arg(group = "scan_source", help = "The files and directories to search")]
+        paths: Vec<PathBuf>,
+        #[arg(
+            long,
+            group = "scan_source",
+            help = "Search the files staged for the next git commit, as they are staged"
+        )]
+        git_staged: bool,
+        #[arg(long, help = "Only search for the values of secrets in this project")]
+        project_id: Option<Uuid>,
+        #[arg(
+            long,
+            default_value_t = 8,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Ignore secret values shorter than this, which would match by chance"
+        )]
+        min_length: u64,
+    },
+    #[command(
+        long_about = "Report stale, duplicated, weak and badly named secrets. Exits with 10 when a finding reaches the --fail-on severity"
+    )]
+    Report {
+        #[arg(long, help = "Only report on the secrets in this project")]
         project_id: Option<Uuid>,
         #[arg(
             long,
-            global = true,
-            env = UUIDS_AS_KEYNAMES_VAR_NAME,
-            help = "Use the secret UUID (in its POSIX form) instead of the key name for the environment variable"
+            value_name = "DURATION",
+            default_value = "90d",
+            help = "Report secrets that haven't been revised in this long"
+        )]
+        stale_after: String,
+        #[arg(
+            long,
+            value_name = "BITS",
+            default_value_t = 40.0,
+            help = "Report values with a lower estimated entropy as weak"
+        )]
+        min_entropy: f64,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = Severity::High,
+            help = "The lowest severity that makes the command exit with 10"
+        )]
+        fail_on: Severity,
+    },
+    #[command(
+        long_about = "Browse, edit, create and delete secrets in an interactive terminal UI. Press ? for the keys"
+    )]
+    Tui,
+    #[command(
+        long_about = "Manage the plugins, which are bws-<name> executables on PATH run as `bws <name>`"
+    )]
+    Plugins {
+        #[command(subcommand)]
+        cmd: PluginsCommand,
+    },
+    /// An unknown subcommand, run as a plugin. The build script includes this file but never reads
+    /// the arguments.
+    #[command(external_subcommand)]
+    #[allow(dead_code)]
+    External(Vec<String>),
+    #[command(
+        long_about = "Recreate the projects and secrets of one organization in another, using the access token of each profile"
+    )]
+    Migrate {
+        #[arg(long, help = "The profile of the organization to migrate from")]
+        from_profile: String,
+        #[arg(long, help = "The profile of the organization to migrate to")]
+        to_profile: String,
+        #[arg(
+            long,
+            default_value = "bws-migration.json",
+            help = "The file mapping old IDs to new IDs, used to resume an interrupted migration"
+        )]
+        mapping_file: PathBuf,
+        #[arg(long, help = "Show what would be migrated without changing anything")]
+        dry_run: bool,
+    },
+    #[command(
+        long_about = "Write all the projects and secrets the access token can see to an archive encrypted with a passphrase"
+    )]
+    Backup {
+        #[arg(long, value_name = "FILE", help = "The file to write the archive to")]
+        out: PathBuf,
+        #[command(flatten)]
+        passphrase: PassphraseArgs,
+    },
+    #[command(
+        long_about = "Recreate the projects and secrets of an archive written by `bws backup`"
+    )]
+    Restore {
+        #[arg(help = "The archive to restore")]
+        file: PathBuf,
+        #[command(flatten)]
+        passphrase: PassphraseArgs,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = ConflictPolicy::Skip,
+            help = "What to do with projects and secrets that already exist"
+        )]
+        on_conflict: ConflictPolicy,
+        #[arg(long, help = "Show what would be restored without changing anything")]
+        dry_run: bool,
+    },
+    #[command(long_about = "Recreate projects and secrets removed by a delete command")]
+    Undo {
+        #[arg(
+            long,
+            help = "Undo the delete with this journal ID, instead of the most recent one"
         )]
-        uuids_as_keynames: bool,
+        id: Option<Uuid>,
+    },
+    #[command(long_about = "Pass secrets to the later steps of a CI job")]
+    Ci {
+        #[command(subcommand)]
+        cmd: CiCommand,
+    },
+    #[command(long_about = "Work with the local audit log of changes made with bws")]
+    Audit {
+        #[command(subcommand)]
+        cmd: AuditCommand,
+    },
+    #[command(long_about = "Inspect the local journal of deleted projects and secrets")]
+    Journal {
+        #[command(subcommand)]
+        cmd: JournalCommand,
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub(crate) enum AuditCommand {
+    #[command(long_about = "Check that no entry of the audit log was modified or removed")]
+    Verify {
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "The audit log to check, instead of the one set by the profile"
+        )]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum PluginsCommand {
+    #[command(long_about = "List the plugins found on PATH")]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum JournalCommand {
+    #[command(long_about = "List the deletes that can be undone, without their values")]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum ConfigCommand {
+    #[command(long_about = "List the profiles in the config file")]
+    List,
+    #[command(long_about = "Show the effective settings and where each value comes from")]
+    Show,
+    #[command(long_about = "Validate the profiles in the config file")]
+    Validate,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum CiCommand {
+    #[command(
+        long_about = "Mask the secret values in the job log and export them to the later steps of the job"
+    )]
+    Export {
+        #[arg(
+            long,
+            value_enum,
+            help = "The CI system, detected from its environment variables when not given"
+        )]
+        provider: Option<CiProvider>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            default_value = "bws.env",
+            help = "The dotenv report to write for GitLab, which the job has to list under artifacts:reports:dotenv"
+        )]
+        dotenv_file: PathBuf,
+        #[command(flatten)]
+        selection: SelectionArgs,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub(crate) enum CiProvider {
+    Github,
+    Gitlab,
+}
+
+/// Which secrets `bws run` and `bws ci export` use, and what they're named.
+#[derive(Args, Debug)]
+pub(crate) struct SelectionArgs {
+    #[arg(long, help = "The ID of the project to use")]
+    pub(crate) project_id: Option<Uuid>,
+    #[arg(
+        long,
+        conflicts_with = "project_id",
+        help = format!("The stage from {} whose project to use", LOCAL_CONFIG_FILENAME)
+    )]
+    pub(crate) stage: Option<String>,
+    #[arg(
+        long = "tag",
+        value_name = "TAG",
+        help = "Only use the secrets with this tag. Can be repeated to require several tags"
+    )]
+    pub(crate) tags: Vec<String>,
+    #[arg(
+        long,
+        global = true,
+        env = UUIDS_AS_KEYNAMES_VAR_NAME,
+        help = "Use the secret UUID (in its POSIX form) instead of the key name for the environment variable"
+    )]
+    pub(crate) uuids_as_keynames: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct PassphraseArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Read the passphrase from the first line of this file, instead of BWS_BACKUP_PASSPHRASE or a prompt"
+    )]
+    pub(crate) passphrase_file: Option<PathBuf>,
+}
+
+/// How `bws restore` treats projects with the same name and secrets with the same key in the same
+/// project.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub(crate) enum ConflictPolicy {
+    /// Keep what exists. Existing projects are reused for the secrets of the archive
+    Skip,
+    /// Update existing secrets with the values of the archive
+    Overwrite,
+    /// Create everything from the archive, even if it already exists
+    Duplicate,
+}
+
 #[derive(Subcommand, Debug)]
 pub(crate) enum SecretCommand {
     Create {
@@ -126,19 +481,92 @@ pub(crate) enum SecretCommand {
         #[arg(long, help = "An optional note to add to the secret")]
         note: Option<String>,
     },
+    #[clap(group = ArgGroup::new("delete_selector").required(true).multiple(true))]
     Delete {
+        #[arg(group = "delete_selector", conflicts_with_all = ["project_id", "key", "older_than"])]
         secret_ids: Vec<Uuid>,
+        #[arg(
+            long,
+            group = "delete_selector",
+            help = "Delete the secrets in this project"
+        )]
+        project_id: Option<Uuid>,
+        #[arg(
+            long,
+            value_name = "GLOB",
+            group = "delete_selector",
+            help = "Delete the secrets with a key matching this glob, such as 'OLD_*'"
+        )]
+        key: Option<String>,
+        #[arg(
+            long,
+            value_name = "DURATION",
+            group = "delete_selector",
+            help = "Delete the secrets that haven't been modified in this long, such as 90d"
+        )]
+        older_than: Option<String>,
+        #[arg(
+            long,
+            help = "Show the secrets that would be deleted without deleting them"
+        )]
+        dry_run: bool,
     },
     #[clap(group = ArgGroup::new("edit_field").required(true).multiple(true))]
+    #[clap(group = ArgGroup::new("edit_selector").required(true).multiple(true))]
     Edit {
-        secret_id: Uuid,
-        #[arg(long, group = "edit_field")]
+        #[arg(
+            group = "edit_selector",
+            conflicts_with_all = ["filter_project_id", "filter_key", "filter_older_than"]
+        )]
+        secret_id: Option<Uuid>,
+        #[arg(
+            long,
+            group = "edit_field",
+            conflicts_with_all = ["filter_project_id", "filter_key", "filter_older_than"]
+        )]
         key: Option<String>,
         #[arg(long, group = "edit_field")]
         value: Option<String>,
         #[arg(long, group = "edit_field")]
         note: Option<String>,
-        #[arg(long, group = "edit_field")]
+        #[arg(long, group = "edit_field", help = "Move the secrets to this project")]
+        project_id: Option<Uuid>,
+        #[arg(
+            long,
+            group = "edit_selector",
+            help = "Edit the secrets in this project"
+        )]
+        filter_project_id: Option<Uuid>,
+        #[arg(
+            long,
+            value_name = "GLOB",
+            group = "edit_selector",
+            help = "Edit the secrets with a key matching this glob, such as 'OLD_*'"
+        )]
+        filter_key: Option<String>,
+        #[arg(
+            long,
+            value_name = "DURATION",
+            group = "edit_selector",
+            help = "Edit the secrets that haven't been modified in this long, such as 90d"
+        )]
+        filter_older_than: Option<String>,
+        #[arg(
+            long,
+            help = "Show the secrets as they would be after the edit, without editing them"
+        )]
+        dry_run: bool,
+    },
+    #[command(long_about = "List the secrets whose note gives an expiry date within a duration")]
+    Expiring {
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "30d",
+            help = "List the secrets expiring within this duration, such as 30d, including expired ones"
+        )]
+        within: String,
+        #[arg(long, help = "Only list the secrets in this project")]
         project_id: Option<Uuid>,
     },
     Get {
@@ -146,6 +574,69 @@ pub(crate) enum SecretCommand {
     },
     List {
         project_id: Option<Uuid>,
+        #[arg(
+            long = "tag",
+            value_name = "TAG",
+            help = "Only list the secrets with this tag. Can be repeated to require several tags"
+        )]
+        tags: Vec<String>,
+    },
+    #[command(long_about = "Rename secret keys with a regex replacement or a built-in transform")]
+    #[clap(group = ArgGroup::new("rename_transform").required(true).multiple(true))]
+    Rename {
+        #[arg(
+            long,
+            help = "The project whose secrets are renamed. Keys only have to be unique within a project"
+        )]
+        project_id: Uuid,
+        #[arg(
+            long,
+            value_name = "REGEX",
+            help = "Only rename the secrets with a key matching this regex"
+        )]
+        pattern: Option<String>,
+        #[arg(
+            long,
+            value_name = "TEMPLATE",
+            requires = "pattern",
+            group = "rename_transform",
+            help = "Replace the matches of --pattern with this template, where $1 or ${name} refer to capture groups"
+        )]
+        replace: Option<String>,
+        #[arg(
+            long,
+            group = "rename_transform",
+            conflicts_with = "to_lower_snake",
+            help = "Convert the keys to UPPER_SNAKE_CASE"
+        )]
+        to_upper_snake: bool,
+        #[arg(
+            long,
+            group = "rename_transform",
+            help = "Convert the keys to lower_snake_case"
+        )]
+        to_lower_snake: bool,
+        #[arg(long, help = "Show the renames without applying them")]
+        dry_run: bool,
+    },
+    #[command(long_about = "Add or remove tags in the front-matter of a secret's note")]
+    Tag {
+        #[command(subcommand)]
+        cmd: TagCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum TagCommand {
+    Add {
+        secret_id: Uuid,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    Remove {
+        secret_id: Uuid,
+        #[arg(required = true)]
+        tags: Vec<String>,
     },
 }
 
@@ -166,4 +657,69 @@ pub(crate) enum ProjectCommand {
         project_id: Uuid,
     },
     List,
+    #[command(long_about = "Copy secrets from one project to another")]
+    #[clap(group = ArgGroup::new("copy_target").required(true))]
+    Copy {
+        #[arg(long, help = "The project to copy secrets from")]
+        from: Uuid,
+        #[arg(long, group = "copy_target", help = "The project to copy secrets to")]
+        to: Option<Uuid>,
+        #[arg(
+            long,
+            value_name = "NAME",
+            group = "copy_target",
+            help = "Create a new project with this name and copy secrets to it"
+        )]
+        create_target: Option<String>,
+        #[arg(
+            long,
+            value_name = "GLOB",
+            value_delimiter = ',',
+            help = "Only copy the secrets with a key matching one of these globs"
+        )]
+        keys: Vec<String>,
+        #[arg(long, value_enum, default_value_t = CopyMode::MissingOnly, help = "Which secrets to create or update in the target project")]
+        mode: CopyMode,
+        #[arg(
+            long,
+            default_value = "CHANGE_ME",
+            help = "The value of the secrets created with --mode keys-only"
+        )]
+        placeholder: String,
+        #[arg(long, help = "Show the changes without applying them")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub(crate) enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub(crate) enum CopyMode {
+    /// Only create the secrets that don't exist in the target project
+    MissingOnly,
+    /// Also update the value and note of the secrets that exist in the target project
+    Overwrite,
+    /// Only create the missing keys, with a placeholder value to be filled in later
+    KeysOnly,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum StateCommand {
+    List,
+    #[command(long_about = "Delete all state files")]
+    Clear,
+    #[command(long_about = "Delete state files that haven't been used recently")]
+    Prune {
+        #[arg(
+            long,
+            default_value = "30d",
+            help = "Delete state files not used within this duration (e.g. 12h, 30d, 2w)"
+        )]
+        older_than: String,
+    },
 }
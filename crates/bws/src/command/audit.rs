@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{bail, Result};
+
+use crate::{
+    audit,
+    error::{CliError, ErrorCategory},
+    AuditCommand,
+};
+
+pub(crate) fn process_command(command: AuditCommand, audit_log: Option<PathBuf>) -> Result<()> {
+    match command {
+        AuditCommand::Verify { file } => {
+            let Some(path) = file.or(audit_log) else {
+                bail!(CliError::new(
+                    ErrorCategory::Validation,
+                    "The profile has no `audit_log`. Pass --file to check another log."
+                ));
+            };
+
+            let (count, last_hash) = audit::verify(&path)?;
+            println!("{count} audit log entries verified. The last hash is {last_hash}.");
+            Ok(())
+        }
+    }
+}
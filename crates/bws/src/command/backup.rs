@@ -0,0 +1,360 @@
+use std::{collections::HashMap, io::IsTerminal, path::PathBuf};
+
+use bitwarden::{
+    secrets_manager::{
+        projects::{ProjectCreateRequest, ProjectsListRequest},
+        secrets::{
+            SecretCreateRequest, SecretIdentifiersRequest, SecretPutRequest, SecretResponse,
+            SecretsGetRequest,
+        },
+        ClientProjectsExt, ClientSecretsExt,
+    },
+    Client,
+};
+use chrono::Utc;
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    backup::{self, BackupData, BackupProject, BackupSecret, KdfParams},
+    confirm::Confirmation,
+    error::{report_partial_failure, CliError, ErrorCategory},
+    render::{serialize_response, OutputSettings},
+    util::{exclude_from_timeouts, write_private_file},
+    ConflictPolicy, PassphraseArgs,
+};
+
+const BACKUP_PASSPHRASE_VAR_NAME: &str = "BWS_BACKUP_PASSPHRASE";
+
+#[derive(Debug)]
+pub(crate) struct RestoreCommandModel {
+    pub(crate) file: PathBuf,
+    pub(crate) passphrase: PassphraseArgs,
+    pub(crate) on_conflict: ConflictPolicy,
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RestoreKind {
+    Project,
+    Secret,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RestoreAction {
+    Create,
+    Update,
+    Skip,
+}
+
+/// What restoring does with a single item of the archive, and the ID it has afterwards.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RestoreItem {
+    pub(crate) kind: RestoreKind,
+    pub(crate) name: String,
+    pub(crate) action: RestoreAction,
+    pub(crate) old_id: Uuid,
+    pub(crate) new_id: Option<Uuid>,
+}
+
+pub(crate) async fn backup(
+    client: Client,
+    organization_id: Uuid,
+    out: PathBuf,
+    passphrase: PassphraseArgs,
+) -> Result<()> {
+    if out.exists() {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!("{} already exists", out.display())
+        ));
+    }
+    let passphrase = get_passphrase(passphrase, true)?;
+
+    let projects = client
+        .projects()
+        .list(&ProjectsListRequest { organization_id })
+        .await?
+        .data
+        .into_iter()
+        .map(|p| BackupProject {
+            id: p.id,
+            name: p.name,
+        })
+        .collect();
+    let secrets = get_all_secrets(&client, organization_id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    let data = BackupData {
+        created: Utc::now(),
+        organization_id,
+        projects,
+        secrets,
+    };
+    let archive = backup::encrypt(&data, &passphrase, KdfParams::default())?;
+    write_private_file(&out, archive)?;
+
+    println!(
+        "{} projects and {} secrets written to {}.",
+        data.projects.len(),
+        data.secrets.len(),
+        out.display()
+    );
+
+    Ok(())
+}
+
+pub(crate) async fn restore(
+    client: Client,
+    organization_id: Uuid,
+    restore: RestoreCommandModel,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+) -> Result<()> {
+    let archive = std::fs::read(&restore.file)
+        .wrap_err_with(|| format!("Failed to read {}", restore.file.display()))?;
+    let data = backup::decrypt(&archive, &get_passphrase(restore.passphrase, false)?)?;
+
+    let existing_projects: HashMap<String, Uuid> = client
+        .projects()
+        .list(&ProjectsListRequest { organization_id })
+        .await?
+        .data
+        .into_iter()
+        .map(|p| (p.name, p.id))
+        .collect();
+    let existing_secrets: HashMap<(Option<Uuid>, String), Uuid> =
+        get_all_secrets(&client, organization_id)
+            .await?
+            .into_iter()
+            .map(|s| ((s.project_id, s.key), s.id))
+            .collect();
+
+    let mut items = Vec::new();
+    let mut project_ids = HashMap::new();
+    for project in &data.projects {
+        let existing = existing_projects.get(&project.name).copied();
+        let action = match (existing, restore.on_conflict) {
+            (Some(_), ConflictPolicy::Skip | ConflictPolicy::Overwrite) => RestoreAction::Skip,
+            _ => RestoreAction::Create,
+        };
+        let new_id = match action {
+            RestoreAction::Skip => existing,
+            _ => None,
+        };
+        if let Some(new_id) = new_id {
+            project_ids.insert(project.id, new_id);
+        }
+
+        items.push(RestoreItem {
+            kind: RestoreKind::Project,
+            name: project.name.clone(),
+            action,
+            old_id: project.id,
+            new_id,
+        });
+    }
+
+    // Secrets can only conflict within a project that exists already
+    let existing_secret = |secret: &BackupSecret, project_ids: &HashMap<Uuid, Uuid>| {
+        let project_id = match secret.project_id {
+            Some(id) => Some(*project_ids.get(&id)?),
+            None => None,
+        };
+        existing_secrets
+            .get(&(project_id, secret.key.clone()))
+            .copied()
+    };
+
+    if restore.dry_run {
+        for secret in &data.secrets {
+            let existing = existing_secret(secret, &project_ids);
+            items.push(plan_secret(secret, existing, restore.on_conflict));
+        }
+
+        serialize_response(items, output_settings);
+        return Ok(());
+    }
+
+    confirmation.confirm(
+        &format!(
+            "Restore {} projects and {} secrets?",
+            data.projects.len(),
+            data.secrets.len()
+        ),
+        &[],
+    )?;
+
+    for item in items
+        .iter_mut()
+        .filter(|i| i.action == RestoreAction::Create)
+    {
+        let project = client
+            .projects()
+            .create(&ProjectCreateRequest {
+                organization_id,
+                name: item.name.clone(),
+            })
+            .await?;
+        audit::record(AuditAction::Create, [(project.id, project.name)])?;
+        item.new_id = Some(project.id);
+        project_ids.insert(item.old_id, project.id);
+    }
+
+    let mut secrets_failed = Vec::new();
+    for secret in &data.secrets {
+        let project_id = secret
+            .project_id
+            .and_then(|id| project_ids.get(&id).copied());
+        let existing = existing_secret(secret, &project_ids);
+        let mut item = plan_secret(secret, existing, restore.on_conflict);
+
+        let result = match (item.action, existing) {
+            (RestoreAction::Create, _) => {
+                client
+                    .secrets()
+                    .create(&SecretCreateRequest {
+                        organization_id,
+                        key: secret.key.clone(),
+                        value: secret.value.clone(),
+                        note: secret.note.clone(),
+                        project_ids: project_id.map(|id| vec![id]),
+                    })
+                    .await
+            }
+            (RestoreAction::Update, Some(id)) => {
+                client
+                    .secrets()
+                    .update(&SecretPutRequest {
+                        id,
+                        organization_id,
+                        key: secret.key.clone(),
+                        value: secret.value.clone(),
+                        note: secret.note.clone(),
+                        project_ids: project_id.map(|id| vec![id]),
+                    })
+                    .await
+            }
+            _ => {
+                items.push(item);
+                continue;
+            }
+        };
+
+        match result {
+            Ok(new_secret) => {
+                let action = match item.action {
+                    RestoreAction::Update => AuditAction::Update,
+                    _ => AuditAction::Create,
+                };
+                audit::record(action, [(new_secret.id, new_secret.key)])?;
+                item.new_id = Some(new_secret.id);
+                items.push(item);
+            }
+            Err(e) => secrets_failed.push((secret.id, e.to_string())),
+        }
+    }
+
+    serialize_response(items, output_settings);
+
+    report_partial_failure(
+        "secret",
+        "Errors when attempting to restore secrets.",
+        &secrets_failed,
+    )?;
+
+    Ok(())
+}
+
+fn plan_secret(
+    secret: &BackupSecret,
+    existing: Option<Uuid>,
+    on_conflict: ConflictPolicy,
+) -> RestoreItem {
+    let (action, new_id) = match (existing, on_conflict) {
+        (Some(id), ConflictPolicy::Skip) => (RestoreAction::Skip, Some(id)),
+        (Some(id), ConflictPolicy::Overwrite) => (RestoreAction::Update, Some(id)),
+        _ => (RestoreAction::Create, None),
+    };
+
+    RestoreItem {
+        kind: RestoreKind::Secret,
+        name: secret.key.clone(),
+        action,
+        old_id: secret.id,
+        new_id,
+    }
+}
+
+async fn get_all_secrets(client: &Client, organization_id: Uuid) -> Result<Vec<SecretResponse>> {
+    let secret_ids: Vec<Uuid> = client
+        .secrets()
+        .list(&SecretIdentifiersRequest { organization_id })
+        .await?
+        .data
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    if secret_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(client
+        .secrets()
+        .get_by_ids(SecretsGetRequest { ids: secret_ids })
+        .await?
+        .data)
+}
+
+/// Reads the passphrase from `--passphrase-file`, `BWS_BACKUP_PASSPHRASE` or a prompt, in that
+/// order. New passphrases are asked for twice, so a typo doesn't make the archive unreadable.
+fn get_passphrase(passphrase: PassphraseArgs, confirm: bool) -> Result<String> {
+    let passphrase = match passphrase.passphrase_file {
+        Some(file) => std::fs::read_to_string(&file)
+            .wrap_err_with(|| format!("Failed to read {}", file.display()))?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        None => match std::env::var(BACKUP_PASSPHRASE_VAR_NAME) {
+            Ok(passphrase) => passphrase,
+            Err(_) if std::io::stdin().is_terminal() => prompt_passphrase(confirm)?,
+            Err(_) => String::new(),
+        },
+    };
+
+    if passphrase.is_empty() {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!(
+                "Missing passphrase. Use --passphrase-file or set {BACKUP_PASSPHRASE_VAR_NAME}"
+            )
+        ));
+    }
+
+    Ok(passphrase)
+}
+
+fn prompt_passphrase(confirm: bool) -> Result<String> {
+    let passphrase = exclude_from_timeouts(|| rpassword::prompt_password("Passphrase: "))?;
+    if confirm
+        && !passphrase.is_empty()
+        && exclude_from_timeouts(|| rpassword::prompt_password("Repeat the passphrase: "))?
+            != passphrase
+    {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "The passphrases don't match"
+        ));
+    }
+
+    Ok(passphrase)
+}
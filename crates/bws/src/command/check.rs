@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use bitwarden::{
+    secrets_manager::{
+        secrets::{SecretIdentifiersByProjectRequest, SecretIdentifiersRequest},
+        ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{bail, Result};
+use uuid::Uuid;
+
+use crate::{
+    error::{CliError, ErrorCategory},
+    LOCAL_CONFIG_FILENAME,
+};
+
+pub(crate) async fn check(
+    client: Client,
+    organization_id: Uuid,
+    project_id: Option<Uuid>,
+    required: Vec<String>,
+) -> Result<()> {
+    if required.is_empty() {
+        bail!("No required secrets are listed in {LOCAL_CONFIG_FILENAME}");
+    }
+
+    let res = if let Some(project_id) = project_id {
+        client
+            .secrets()
+            .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
+            .await?
+    } else {
+        client
+            .secrets()
+            .list(&SecretIdentifiersRequest { organization_id })
+            .await?
+    };
+
+    let keys: HashSet<String> = res.data.into_iter().map(|s| s.key).collect();
+    let missing: Vec<&String> = required.iter().filter(|k| !keys.contains(*k)).collect();
+
+    if missing.is_empty() {
+        println!("All {} required secrets are present.", required.len());
+        return Ok(());
+    }
+
+    match missing.len() {
+        2.. => eprintln!("{} required secrets are missing:", missing.len()),
+        _ => eprintln!("1 required secret is missing:"),
+    }
+
+    for key in &missing {
+        eprintln!("{}", key);
+    }
+
+    bail!(CliError::new(
+        ErrorCategory::NotFound,
+        "Errors when checking required secrets."
+    )
+    .with_details(missing.into_iter().cloned().collect()));
+}
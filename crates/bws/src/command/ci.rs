@@ -0,0 +1,221 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use bitwarden::Client;
+use chrono::TimeDelta;
+use color_eyre::eyre::{bail, Result};
+use uuid::Uuid;
+
+use crate::{
+    command::run::fetch_environment,
+    error::{CliError, ErrorCategory},
+    util::{is_valid_posix_name, write_private_file},
+    CiProvider,
+};
+
+#[derive(Debug)]
+pub(crate) struct CiExportCommandModel {
+    pub(crate) provider: Option<CiProvider>,
+    pub(crate) dotenv_file: PathBuf,
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) uuids_as_keynames: bool,
+}
+
+pub(crate) async fn export(
+    client: Client,
+    organization_id: Uuid,
+    timeout: Option<TimeDelta>,
+    export: CiExportCommandModel,
+) -> Result<()> {
+    let provider = match export.provider {
+        Some(provider) => provider,
+        None => detect_provider()?,
+    };
+
+    let environment = fetch_environment(
+        &client,
+        organization_id,
+        timeout,
+        export.project_id,
+        &export.tags,
+        export.uuids_as_keynames,
+    )
+    .await?;
+    let secrets = usable_secrets(environment, provider);
+
+    match provider {
+        CiProvider::Github => {
+            let env_file = github_file("GITHUB_ENV")?;
+            let output_file = github_file("GITHUB_OUTPUT")?;
+
+            // Values are masked before anything can print them
+            for value in secrets.values() {
+                for command in mask_commands(value) {
+                    println!("{command}");
+                }
+            }
+
+            let entries: String = secrets
+                .iter()
+                .map(|(key, value)| github_entry(key, value))
+                .collect();
+            append(&env_file, &entries)?;
+            append(&output_file, &entries)?;
+
+            eprintln!(
+                "Exported {} secrets to $GITHUB_ENV and $GITHUB_OUTPUT",
+                secrets.len()
+            );
+        }
+        CiProvider::Gitlab => {
+            let report: String = secrets
+                .iter()
+                .map(|(key, value)| format!("{key}={value}\n"))
+                .collect();
+            write_private_file(&export.dotenv_file, report)?;
+
+            eprintln!(
+                "Exported {} secrets to {}",
+                secrets.len(),
+                export.dotenv_file.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// GitHub Actions and GitLab CI/CD both set a variable to `true` in every job.
+fn detect_provider() -> Result<CiProvider> {
+    let is_set = |name| std::env::var(name).is_ok_and(|v| v == "true");
+
+    if is_set("GITHUB_ACTIONS") {
+        Ok(CiProvider::Github)
+    } else if is_set("GITLAB_CI") {
+        Ok(CiProvider::Gitlab)
+    } else {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "Couldn't detect the CI provider. Pass --provider to choose one."
+        ))
+    }
+}
+
+/// Drops the secrets the provider can't export, sorted by key. Names have to be valid environment
+/// variable names, and GitLab's dotenv reports can't hold values spanning several lines.
+fn usable_secrets(
+    environment: HashMap<String, String>,
+    provider: CiProvider,
+) -> BTreeMap<String, String> {
+    environment
+        .into_iter()
+        .filter(|(key, value)| {
+            if !is_valid_posix_name(key) {
+                eprintln!("Warning: Skipping '{key}', which isn't a valid environment variable name");
+                return false;
+            }
+            if provider == CiProvider::Gitlab && value.contains(['\n', '\r']) {
+                eprintln!("Warning: Skipping '{key}', as dotenv reports can't hold values with several lines");
+                return false;
+            }
+            true
+        })
+        .collect()
+}
+
+fn github_file(name: &str) -> Result<PathBuf> {
+    match std::env::var_os(name) {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!("${name} isn't set, so this doesn't look like a GitHub Actions step")
+        )),
+    }
+}
+
+fn append(path: &Path, contents: &str) -> Result<()> {
+    let mut file = match std::fs::OpenOptions::new().append(true).open(path) {
+        Ok(file) => file,
+        Err(e) => bail!("Failed to open {}: {e}", path.display()),
+    };
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// The `::add-mask::` commands hiding a value from the job log. GitHub masks line by line, so each
+/// line of the value is masked on its own.
+fn mask_commands(value: &str) -> Vec<String> {
+    value
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let line = line.replace('%', "%25");
+            format!("::add-mask::{line}")
+        })
+        .collect()
+}
+
+/// A `$GITHUB_ENV` or `$GITHUB_OUTPUT` entry in heredoc form, with a random delimiter that doesn't
+/// appear in the value, so that no value can end the entry early and define other variables.
+fn github_entry(key: &str, value: &str) -> String {
+    let delimiter = loop {
+        let delimiter = format!("ghadelimiter_{}", Uuid::new_v4());
+        if !value.contains(&delimiter) {
+            break delimiter;
+        }
+    };
+
+    format!("{key}<<{delimiter}\n{value}\n{delimiter}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_every_line() {
+        assert_eq!(
+            vec!["::add-mask::first", "::add-mask::100%25"],
+            mask_commands("first\r\n\n100%\n")
+        );
+        assert!(mask_commands("").is_empty());
+    }
+
+    #[test]
+    fn writes_heredoc_entries() {
+        let entry = github_entry("KEY", "a\nEOF\nb");
+        let lines: Vec<&str> = entry.lines().collect();
+
+        assert_eq!(5, lines.len());
+        let delimiter = lines[0].strip_prefix("KEY<<").unwrap();
+        assert!(delimiter.starts_with("ghadelimiter_"));
+        assert_eq!(["a", "EOF", "b", delimiter], lines[1..]);
+    }
+
+    #[test]
+    fn skips_what_the_provider_cannot_export() {
+        let environment = HashMap::from([
+            ("API_KEY".to_string(), "value".to_string()),
+            ("CERT".to_string(), "line 1\nline 2".to_string()),
+            ("my-key".to_string(), "value".to_string()),
+        ]);
+
+        assert_eq!(
+            vec!["API_KEY", "CERT"],
+            usable_secrets(environment.clone(), CiProvider::Github)
+                .into_keys()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["API_KEY"],
+            usable_secrets(environment, CiProvider::Gitlab)
+                .into_keys()
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use bitwarden::auth::AccessToken;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, ValueEnum};
+use color_eyre::eyre::{bail, Result};
+
+use crate::{
+    cli::Output,
+    config::{self, Config, ConfigEntry, ConfigSource, ProfileSummary},
+    error::{CliError, ErrorCategory},
+    local_config::LocalConfig,
+    render::{serialize_response, OutputSettings},
+    Cli, ConfigCommand, ProfileKey,
+};
+
+pub(crate) fn process_command(
+    command: ConfigCommand,
+    matches: &ArgMatches,
+    local_config: &LocalConfig,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let config_file = matches.get_one::<PathBuf>("config_file").cloned();
+
+    match command {
+        ConfigCommand::List => list(config_file, output_settings),
+        ConfigCommand::Show => show(matches, local_config, output_settings),
+        ConfigCommand::Validate => {
+            validate(config_file, matches.get_one::<String>("profile").cloned())
+        }
+    }
+}
+
+pub(crate) fn update(
+    name: Option<ProfileKey>,
+    value: Option<String>,
+    delete: bool,
+    profile: Option<String>,
+    access_token: Option<String>,
+    config_file: Option<PathBuf>,
+) -> Result<()> {
+    let profile = if let Some(profile) = profile {
+        profile
+    } else if let Some(access_token) = access_token {
+        AccessToken::from_str(&access_token)?
+            .access_token_id
+            .to_string()
+    } else {
+        String::from("default")
+    };
+
+    if delete {
+        config::delete_profile(config_file.as_deref(), profile)?;
+        println!("Profile deleted successfully!");
+    } else {
+        let (name, value) = match (name, value) {
+            (None, None) => bail!("Missing `name` and `value`"),
+            (None, Some(_)) => bail!("Missing `value`"),
+            (Some(_), None) => bail!("Missing `name`"),
+            (Some(name), Some(value)) => match name.validate_value(&value) {
+                Ok(()) => (name, value),
+                Err(e) => bail!("Profile key \"{}\": {}", name.name(), e),
+            },
+        };
+
+        config::update_profile(config_file.as_deref(), profile, name, value)?;
+        println!("Profile updated successfully!");
+    };
+
+    Ok(())
+}
+
+pub(crate) fn list(config_file: Option<PathBuf>, output_settings: OutputSettings) -> Result<()> {
+    let config = config::load_config(config_file.as_deref(), config_file.is_some())?;
+
+    let mut profiles: Vec<ProfileSummary> = config
+        .profiles
+        .into_iter()
+        .map(|(name, profile)| ProfileSummary {
+            name,
+            api_url: profile.api_url().ok(),
+            identity_url: profile.identity_url().ok(),
+        })
+        .collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    serialize_response(profiles, output_settings);
+
+    Ok(())
+}
+
+pub(crate) fn show(
+    matches: &ArgMatches,
+    local_config: &LocalConfig,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let mut entries = Vec::new();
+
+    let config_file = matches.get_one::<PathBuf>("config_file").cloned();
+    entries.push(ConfigEntry {
+        key: "config_file".to_string(),
+        value: config::get_config_path(config_file.as_deref(), false)?
+            .display()
+            .to_string(),
+        source: value_source(matches, "config_file"),
+    });
+    let config = config::load_config(config_file.as_deref(), config_file.is_some())?;
+
+    let access_token = matches.get_one::<String>("access_token");
+    let access_token_id = access_token.map(|t| match AccessToken::from_str(t) {
+        Ok(t) => t.access_token_id.to_string(),
+        Err(_) => "<invalid>".to_string(),
+    });
+    if matches.get_flag("access_token_stdin") {
+        entries.push(ConfigEntry {
+            key: "access_token".to_string(),
+            value: "<stdin>".to_string(),
+            source: ConfigSource::Flag,
+        });
+    } else if let Some(access_token_id) = &access_token_id {
+        entries.push(ConfigEntry {
+            key: "access_token_id".to_string(),
+            value: access_token_id.clone(),
+            source: value_source(matches, "access_token"),
+        });
+    }
+
+    let (profile_name, profile_source) =
+        resolve_profile_name(matches, local_config, &config, access_token_id);
+
+    if let Some(server_url) = matches.get_one::<String>("server_url") {
+        entries.push(ConfigEntry {
+            key: "server_base".to_string(),
+            value: server_url.clone(),
+            source: value_source(matches, "server_url"),
+        });
+    } else {
+        entries.push(ConfigEntry {
+            key: "profile".to_string(),
+            value: profile_name.clone(),
+            source: profile_source,
+        });
+
+        if let Some(profile) = config.profiles.get(&profile_name) {
+            for key in ProfileKey::value_variants() {
+                if let Some(value) = key.profile_value(profile) {
+                    entries.push(ConfigEntry {
+                        key: key.name(),
+                        value: value.clone(),
+                        source: ConfigSource::File,
+                    });
+                }
+            }
+        }
+    }
+
+    for (key, value) in [
+        (
+            "output",
+            matches
+                .get_one::<Output>("output")
+                .copied()
+                .and_then(|o| o.to_possible_value()),
+        ),
+        (
+            "color",
+            matches
+                .get_one::<bitwarden_cli::Color>("color")
+                .copied()
+                .and_then(|c| c.to_possible_value()),
+        ),
+    ] {
+        // Explicit flags override the profile, which in turn overrides the flag defaults
+        let source = value_source(matches, key);
+        if source == ConfigSource::Default {
+            if entries.iter().any(|e| e.key == key) {
+                continue;
+            }
+        } else {
+            entries.retain(|e| e.key != key);
+        }
+
+        if let Some(value) = value {
+            entries.push(ConfigEntry {
+                key: key.to_string(),
+                value: value.get_name().to_string(),
+                source,
+            });
+        }
+    }
+
+    for (name, alias) in &config.aliases {
+        entries.push(ConfigEntry {
+            key: format!("alias.{name}"),
+            value: alias.to_string(),
+            source: ConfigSource::File,
+        });
+    }
+
+    serialize_response(entries, output_settings);
+
+    Ok(())
+}
+
+pub(crate) fn validate(config_file: Option<PathBuf>, profile: Option<String>) -> Result<()> {
+    let config = config::load_config(config_file.as_deref(), true)?;
+
+    let mut profiles: Vec<_> = match &profile {
+        Some(profile) => match config.profiles.get(profile) {
+            Some(p) => vec![(profile, p)],
+            None => bail!(CliError::new(
+                ErrorCategory::NotFound,
+                "The specified profile does not exist"
+            )),
+        },
+        None => config.profiles.iter().collect(),
+    };
+    profiles.sort_by(|a, b| a.0.cmp(b.0));
+
+    let problems: Vec<String> = profiles
+        .iter()
+        .flat_map(|(name, profile)| {
+            profile
+                .validate()
+                .into_iter()
+                .map(move |problem| format!("[{name}] {problem}"))
+        })
+        .chain(alias_problems(&config))
+        .collect();
+
+    for (name, profile) in &profiles {
+        if let Some(state_dir) = profile.state_dir.as_deref() {
+            if !Path::new(state_dir).exists() {
+                eprintln!(
+                    "[{name}] state_dir: `{state_dir}` doesn't exist yet and will be created"
+                );
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        println!("Config file is valid.");
+        return Ok(());
+    }
+
+    match problems.len() {
+        2.. => eprintln!("{} problems found:", problems.len()),
+        _ => eprintln!("1 problem found:"),
+    }
+
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+
+    bail!(CliError::new(
+        ErrorCategory::Validation,
+        "Errors when validating the config file."
+    )
+    .with_details(problems));
+}
+
+/// Aliases named like a built-in command are never used, as built-in commands are matched first.
+fn alias_problems(config: &Config) -> Vec<String> {
+    let command = Cli::command();
+
+    config
+        .aliases
+        .iter()
+        .filter_map(|(name, alias)| {
+            if command.find_subcommand(name).is_some() {
+                return Some(format!(
+                    "[aliases] `{name}` is a built-in command, so the alias is never used"
+                ));
+            }
+            alias
+                .words()
+                .err()
+                .map(|e| format!("[aliases] `{name}`: {e}"))
+        })
+        .collect()
+}
+
+/// Returns the name of the profile that will be used and where it was chosen, mirroring the profile
+/// resolution of `get_config_profile`.
+pub(crate) fn resolve_profile_name(
+    matches: &ArgMatches,
+    local_config: &LocalConfig,
+    config: &Config,
+    access_token_id: Option<String>,
+) -> (String, ConfigSource) {
+    match matches.get_one::<String>("profile") {
+        Some(profile) => (profile.clone(), value_source(matches, "profile")),
+        None => match (&local_config.profile, access_token_id) {
+            (Some(profile), _) => (profile.clone(), ConfigSource::LocalFile),
+            (None, Some(id)) if config.profiles.contains_key(&id) => {
+                (id, ConfigSource::AccessToken)
+            }
+            _ => ("default".to_string(), ConfigSource::Default),
+        },
+    }
+}
+
+pub(crate) fn value_source(matches: &ArgMatches, id: &str) -> ConfigSource {
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => ConfigSource::Flag,
+        Some(ValueSource::EnvVariable) => ConfigSource::Env,
+        _ => ConfigSource::Default,
+    }
+}
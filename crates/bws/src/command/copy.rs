@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use bitwarden::{
+    secrets_manager::{
+        projects::ProjectCreateRequest,
+        secrets::{
+            SecretCreateRequest, SecretIdentifiersByProjectRequest, SecretPutRequest,
+            SecretResponse, SecretsGetRequest,
+        },
+        ClientProjectsExt, ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{bail, Result};
+use regex::Regex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    confirm::Confirmation,
+    error::report_partial_failure,
+    render::{serialize_response, OutputSettings},
+    util, CopyMode,
+};
+
+#[derive(Debug)]
+pub(crate) struct ProjectCopyCommandModel {
+    pub(crate) from: Uuid,
+    pub(crate) to: Option<Uuid>,
+    pub(crate) create_target: Option<String>,
+    pub(crate) keys: Vec<String>,
+    pub(crate) mode: CopyMode,
+    pub(crate) placeholder: String,
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CopyAction {
+    Create,
+    Update,
+    Unchanged,
+    Skip,
+}
+
+impl std::fmt::Display for CopyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self {
+            CopyAction::Create => "create",
+            CopyAction::Update => "update",
+            CopyAction::Unchanged => "unchanged",
+            CopyAction::Skip => "skip",
+        };
+        write!(f, "{action}")
+    }
+}
+
+/// What copying will do to a single key of the target project. Values are never shown.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CopyChange {
+    pub(crate) key: String,
+    pub(crate) action: CopyAction,
+    #[serde(skip)]
+    source: usize,
+    #[serde(skip)]
+    target_id: Option<Uuid>,
+}
+
+pub(crate) async fn copy(
+    client: Client,
+    organization_id: Uuid,
+    copy: ProjectCopyCommandModel,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+) -> Result<()> {
+    let keys = copy
+        .keys
+        .iter()
+        .map(|k| util::glob_to_regex(k))
+        .collect::<Result<Vec<_>>>()?;
+
+    let source = get_project_secrets(&client, copy.from).await?;
+    let target = match copy.to {
+        Some(to) => get_project_secrets(&client, to).await?,
+        None => Vec::new(),
+    };
+
+    let changes = plan_copy(&source, &target, copy.mode, &keys);
+    serialize_response(changes.clone(), output_settings);
+
+    let pending = changes
+        .iter()
+        .filter(|c| matches!(c.action, CopyAction::Create | CopyAction::Update))
+        .count();
+    if copy.dry_run {
+        return Ok(());
+    }
+    if pending == 0 && copy.create_target.is_none() {
+        eprintln!("Nothing to copy.");
+        return Ok(());
+    }
+
+    confirmation.confirm(&format!("Copy {pending} secrets?"), &[])?;
+
+    let target_id = match (copy.to, copy.create_target) {
+        (Some(to), _) => to,
+        (None, Some(name)) => {
+            let project = client
+                .projects()
+                .create(&ProjectCreateRequest {
+                    organization_id,
+                    name,
+                })
+                .await?;
+            audit::record(AuditAction::Create, [(project.id, project.name)])?;
+            project.id
+        }
+        (None, None) => bail!("Missing target project"),
+    };
+
+    let mut created = Vec::new();
+    let mut updated = Vec::new();
+    let mut secrets_failed = Vec::new();
+    for change in &changes {
+        let secret = &source[change.source];
+        let value = match copy.mode {
+            CopyMode::KeysOnly => copy.placeholder.clone(),
+            _ => secret.value.clone(),
+        };
+
+        let result = match (change.action, change.target_id) {
+            (CopyAction::Create, _) => {
+                client
+                    .secrets()
+                    .create(&SecretCreateRequest {
+                        organization_id,
+                        key: secret.key.clone(),
+                        value,
+                        note: secret.note.clone(),
+                        project_ids: Some(vec![target_id]),
+                    })
+                    .await
+            }
+            (CopyAction::Update, Some(id)) => {
+                client
+                    .secrets()
+                    .update(&SecretPutRequest {
+                        id,
+                        organization_id,
+                        key: secret.key.clone(),
+                        value,
+                        note: secret.note.clone(),
+                        project_ids: Some(vec![target_id]),
+                    })
+                    .await
+            }
+            _ => continue,
+        };
+
+        match result {
+            Ok(secret) if change.action == CopyAction::Create => {
+                created.push((secret.id, secret.key))
+            }
+            Ok(secret) => updated.push((secret.id, secret.key)),
+            Err(e) => secrets_failed.push((secret.key.clone(), e.to_string())),
+        }
+    }
+
+    let copied = created.len() + updated.len();
+    audit::record(AuditAction::Create, created)?;
+    audit::record(AuditAction::Update, updated)?;
+
+    match copied {
+        2.. => println!("{} secrets copied successfully.", copied),
+        1 => println!("{} secret copied successfully.", copied),
+        _ => (),
+    }
+
+    report_partial_failure(
+        "secret",
+        "Errors when attempting to copy secrets.",
+        &secrets_failed,
+    )?;
+
+    Ok(())
+}
+
+async fn get_project_secrets(client: &Client, project_id: Uuid) -> Result<Vec<SecretResponse>> {
+    let secret_ids: Vec<Uuid> = client
+        .secrets()
+        .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
+        .await?
+        .data
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    if secret_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(client
+        .secrets()
+        .get_by_ids(SecretsGetRequest { ids: secret_ids })
+        .await?
+        .data)
+}
+
+/// Compares the source secrets with the target project by key, deciding what to do with each.
+fn plan_copy(
+    source: &[SecretResponse],
+    target: &[SecretResponse],
+    mode: CopyMode,
+    keys: &[Regex],
+) -> Vec<CopyChange> {
+    let target: HashMap<&str, &SecretResponse> =
+        target.iter().map(|s| (s.key.as_str(), s)).collect();
+
+    let mut changes: Vec<CopyChange> = source
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| keys.is_empty() || keys.iter().any(|k| k.is_match(&s.key)))
+        .map(|(index, secret)| {
+            let existing = target.get(secret.key.as_str());
+            let action = match (existing, mode) {
+                (None, _) => CopyAction::Create,
+                (Some(t), CopyMode::Overwrite)
+                    if t.value == secret.value && t.note == secret.note =>
+                {
+                    CopyAction::Unchanged
+                }
+                (Some(_), CopyMode::Overwrite) => CopyAction::Update,
+                (Some(_), CopyMode::MissingOnly | CopyMode::KeysOnly) => CopyAction::Skip,
+            };
+
+            CopyChange {
+                key: secret.key.clone(),
+                action,
+                source: index,
+                target_id: existing.map(|t| t.id),
+            }
+        })
+        .collect();
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::secret;
+
+    fn actions(changes: &[CopyChange]) -> Vec<(&str, CopyAction)> {
+        changes.iter().map(|c| (c.key.as_str(), c.action)).collect()
+    }
+
+    #[test]
+    fn copy_modes() {
+        let source = [
+            secret("A", "1", None),
+            secret("B", "2", None),
+            secret("C", "3", None),
+        ];
+        let target = [secret("B", "2", None), secret("C", "old", None)];
+
+        assert_eq!(
+            vec![
+                ("A", CopyAction::Create),
+                ("B", CopyAction::Skip),
+                ("C", CopyAction::Skip)
+            ],
+            actions(&plan_copy(&source, &target, CopyMode::MissingOnly, &[]))
+        );
+        assert_eq!(
+            vec![
+                ("A", CopyAction::Create),
+                ("B", CopyAction::Unchanged),
+                ("C", CopyAction::Update)
+            ],
+            actions(&plan_copy(&source, &target, CopyMode::Overwrite, &[]))
+        );
+    }
+
+    #[test]
+    fn copy_filters_keys() {
+        let source = [secret("DB_HOST", "1", None), secret("API_KEY", "2", None)];
+        let keys = [util::glob_to_regex("DB_*").unwrap()];
+
+        assert_eq!(
+            vec![("DB_HOST", CopyAction::Create)],
+            actions(&plan_copy(&source, &[], CopyMode::KeysOnly, &keys))
+        );
+    }
+}
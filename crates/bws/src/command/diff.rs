@@ -0,0 +1,245 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use bitwarden::{
+    secrets_manager::{
+        secrets::{SecretIdentifiersByProjectRequest, SecretsGetRequest},
+        ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{Result, WrapErr};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{
+    error::ErrorCategory,
+    render::{serialize_response, OutputSettings},
+};
+
+#[derive(Debug)]
+pub(crate) struct DiffCommandModel {
+    pub(crate) project_id: Uuid,
+    pub(crate) other: String,
+    pub(crate) keys_only: bool,
+    pub(crate) reveal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DiffStatus {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl std::fmt::Display for DiffStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            DiffStatus::Added => "added",
+            DiffStatus::Removed => "removed",
+            DiffStatus::Changed => "changed",
+        };
+        write!(f, "{status}")
+    }
+}
+
+/// A key that differs between the two sides. `added` keys only exist on the right side, `removed`
+/// keys only on the left.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiffEntry {
+    pub(crate) key: String,
+    pub(crate) status: DiffStatus,
+    pub(crate) left: Option<String>,
+    pub(crate) right: Option<String>,
+}
+
+/// Compares the project with another project or a .env file, returning the exit code: the findings
+/// code when there are differences, so the command can gate CI.
+pub(crate) async fn diff(
+    client: Client,
+    diff: DiffCommandModel,
+    output_settings: OutputSettings,
+) -> Result<i32> {
+    let left = get_project_values(&client, diff.project_id).await?;
+    let right = match Uuid::parse_str(&diff.other) {
+        Ok(project_id) => get_project_values(&client, project_id).await?,
+        Err(_) => read_env_file(Path::new(&diff.other))?,
+    };
+
+    // A new key each run, so hashes can't be looked up in a dictionary or linked across runs
+    let mut hash_key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut hash_key);
+
+    let entries = compare(&left, &right, diff.keys_only, diff.reveal, &hash_key);
+    let exit_code = match entries.is_empty() {
+        true => 0,
+        false => ErrorCategory::Findings.exit_code(),
+    };
+    serialize_response(entries, output_settings);
+
+    Ok(exit_code)
+}
+
+async fn get_project_values(client: &Client, project_id: Uuid) -> Result<BTreeMap<String, String>> {
+    let secret_ids: Vec<Uuid> = client
+        .secrets()
+        .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
+        .await?
+        .data
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    if secret_ids.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    Ok(client
+        .secrets()
+        .get_by_ids(SecretsGetRequest { ids: secret_ids })
+        .await?
+        .data
+        .into_iter()
+        .map(|s| (s.key, s.value))
+        .collect())
+}
+
+fn read_env_file(path: &Path) -> Result<BTreeMap<String, String>> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse_env(&content))
+}
+
+/// Parses `KEY=value` lines, ignoring blank lines, comments and an `export` prefix, and removing
+/// quotes around values.
+fn parse_env(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = [('"', '"'), ('\'', '\'')]
+                .iter()
+                .find_map(|(open, close)| {
+                    value
+                        .strip_prefix(*open)
+                        .and_then(|v| v.strip_suffix(*close))
+                })
+                .unwrap_or(value);
+
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn compare(
+    left: &BTreeMap<String, String>,
+    right: &BTreeMap<String, String>,
+    keys_only: bool,
+    reveal: bool,
+    hash_key: &[u8],
+) -> Vec<DiffEntry> {
+    let show = |value: &String| match reveal {
+        true => value.clone(),
+        false => hash_value(hash_key, value),
+    };
+
+    let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let status = match (left.get(key), right.get(key)) {
+                (Some(_), None) => DiffStatus::Removed,
+                (None, Some(_)) => DiffStatus::Added,
+                (Some(l), Some(r)) if l != r && !keys_only => DiffStatus::Changed,
+                _ => return None,
+            };
+
+            Some(DiffEntry {
+                key: key.clone(),
+                status,
+                left: left.get(key).filter(|_| !keys_only).map(show),
+                right: right.get(key).filter(|_| !keys_only).map(show),
+            })
+        })
+        .collect()
+}
+
+/// A short keyed hash, enough to tell whether values differ without showing them. The key is
+/// random and thrown away after the run, so the hash says nothing about the value itself.
+fn hash_value(key: &[u8], value: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key).expect("HMAC should accept keys of any length");
+    mac.update(value.as_bytes());
+    let hash = mac.finalize().into_bytes();
+    let hex: String = hash.iter().take(6).map(|b| format!("{b:02x}")).collect();
+    format!("hmac:{hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_keys() {
+        let left = values(&[("A", "1"), ("B", "2"), ("C", "3")]);
+        let right = values(&[("B", "2"), ("C", "changed"), ("D", "4")]);
+
+        let key = [7u8; 32];
+        let entries = compare(&left, &right, false, false, &key);
+        let statuses: Vec<(&str, DiffStatus)> =
+            entries.iter().map(|e| (e.key.as_str(), e.status)).collect();
+        assert_eq!(
+            vec![
+                ("A", DiffStatus::Removed),
+                ("C", DiffStatus::Changed),
+                ("D", DiffStatus::Added)
+            ],
+            statuses
+        );
+        assert_eq!(Some(hash_value(&key, "changed")), entries[1].right);
+        assert_ne!(Some("changed".to_string()), entries[1].right);
+        assert_ne!(
+            entries[1].right,
+            compare(&left, &right, false, false, &[8u8; 32])[1].right
+        );
+
+        assert_eq!(2, compare(&left, &right, true, false, &key).len());
+        assert_eq!(
+            Some("changed".to_string()),
+            compare(&left, &right, false, true, &key)[1].right
+        );
+    }
+
+    #[test]
+    fn parses_env_files() {
+        let env = parse_env(
+            "# comment\n\nexport DB_HOST=localhost\nDB_PASSWORD=\"secret\"\nEMPTY=\nAPI_KEY = 'key'\n",
+        );
+
+        assert_eq!(
+            values(&[
+                ("API_KEY", "key"),
+                ("DB_HOST", "localhost"),
+                ("DB_PASSWORD", "secret"),
+                ("EMPTY", "")
+            ]),
+            env
+        );
+    }
+}
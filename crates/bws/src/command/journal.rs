@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use bitwarden::{
+    secrets_manager::{
+        projects::ProjectCreateRequest,
+        secrets::{SecretCreateRequest, SecretGetRequest, SecretPutRequest, SecretResponse},
+        ClientProjectsExt, ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{bail, Result};
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    command::backup::{RestoreAction, RestoreItem, RestoreKind},
+    confirm::Confirmation,
+    error::{report_partial_failure, CliError, ErrorCategory},
+    journal::{DetachedSecret, Journal, JournalEntry, JournalSummary},
+    render::{serialize_response, OutputSettings},
+    JournalCommand,
+};
+
+pub(crate) fn process_command(
+    command: JournalCommand,
+    journal: Option<Journal>,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    match command {
+        JournalCommand::List => {
+            let entries = match journal {
+                Some(journal) => journal.list()?,
+                None => Vec::new(),
+            };
+            let summaries: Vec<JournalSummary> = entries.iter().map(Into::into).collect();
+            serialize_response(summaries, output_settings);
+            Ok(())
+        }
+    }
+}
+
+/// Recreates the projects and secrets of a journal entry. Projects are created first, so secrets
+/// that were in a deleted project are added to its replacement, and the secrets a project delete
+/// left without a project are moved back into it.
+pub(crate) async fn undo(
+    client: Client,
+    organization_id: Uuid,
+    journal: Option<Journal>,
+    id: Option<Uuid>,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+) -> Result<()> {
+    let Some(journal) = journal else {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "The deletion journal is disabled by the `journal_retention` of the profile"
+        ));
+    };
+    let mut entry = journal.get(id)?;
+
+    let names: Vec<(Uuid, String)> = entry
+        .projects
+        .iter()
+        .map(|p| (p.id, p.name.clone()))
+        .chain(entry.secrets.iter().map(|s| (s.id, s.key.clone())))
+        .collect();
+    let mut prompt = format!(
+        "Recreate {} projects and {} secrets",
+        entry.projects.len(),
+        entry.secrets.len()
+    );
+    if !entry.detached.is_empty() {
+        prompt.push_str(&format!(
+            ", and move {} secrets back into the projects",
+            entry.detached.len()
+        ));
+    }
+    confirmation.confirm(&format!("{prompt}?"), &names)?;
+
+    let mut items = Vec::new();
+    let mut project_ids = HashMap::new();
+    let mut projects = std::mem::take(&mut entry.projects).into_iter();
+    while let Some(project) = projects.next() {
+        let result = client
+            .projects()
+            .create(&ProjectCreateRequest {
+                organization_id,
+                name: project.name.clone(),
+            })
+            .await;
+        let created = match result {
+            Ok(created) => created,
+            Err(e) => {
+                // Keep the projects that weren't recreated, and move the secrets to the ones that
+                // were, so a retry doesn't duplicate them
+                entry.projects = std::iter::once(project).chain(projects).collect();
+                move_to_recreated(&mut entry, &project_ids);
+                journal.write(&entry)?;
+                serialize_response(items, output_settings);
+                return Err(e.into());
+            }
+        };
+        audit::record(AuditAction::Create, [(created.id, created.name)])?;
+        project_ids.insert(project.id, created.id);
+        items.push(RestoreItem {
+            kind: RestoreKind::Project,
+            name: project.name.clone(),
+            action: RestoreAction::Create,
+            old_id: project.id,
+            new_id: Some(created.id),
+        });
+    }
+
+    move_to_recreated(&mut entry, &project_ids);
+
+    let mut secrets_failed = Vec::new();
+    let mut remaining = Vec::new();
+    for secret in std::mem::take(&mut entry.secrets) {
+        let result = client
+            .secrets()
+            .create(&SecretCreateRequest {
+                organization_id,
+                key: secret.key.clone(),
+                value: secret.value.clone(),
+                note: secret.note.clone(),
+                project_ids: secret.project_id.map(|id| vec![id]),
+            })
+            .await;
+
+        match result {
+            Ok(created) => {
+                audit::record(AuditAction::Create, [(created.id, created.key)])?;
+                items.push(RestoreItem {
+                    kind: RestoreKind::Secret,
+                    name: secret.key,
+                    action: RestoreAction::Create,
+                    old_id: secret.id,
+                    new_id: Some(created.id),
+                });
+            }
+            Err(e) => {
+                secrets_failed.push((secret.id, e.to_string()));
+                remaining.push(secret);
+            }
+        }
+    }
+
+    let mut detached = Vec::new();
+    for secret in std::mem::take(&mut entry.detached) {
+        match reattach(&client, organization_id, &secret).await {
+            Ok(updated) => {
+                audit::record(AuditAction::Update, [(updated.id, updated.key.clone())])?;
+                items.push(RestoreItem {
+                    kind: RestoreKind::Secret,
+                    name: updated.key,
+                    action: RestoreAction::Update,
+                    old_id: secret.id,
+                    new_id: Some(secret.id),
+                });
+            }
+            Err(e) => {
+                secrets_failed.push((secret.id, e.to_string()));
+                detached.push(secret);
+            }
+        }
+    }
+
+    serialize_response(items, output_settings);
+
+    if secrets_failed.is_empty() {
+        journal.remove(entry.id)?;
+        return Ok(());
+    }
+
+    // Keep what couldn't be recreated, so it can be retried without duplicating the rest
+    entry.projects = Vec::new();
+    entry.secrets = remaining;
+    entry.detached = detached;
+    journal.write(&entry)?;
+
+    report_partial_failure(
+        "secret",
+        format!(
+            "Errors when attempting to restore secrets. Run `bws undo --id {}` to retry.",
+            entry.id
+        ),
+        &secrets_failed,
+    )
+}
+
+/// Points the secrets of the entry at the projects that were recreated so far.
+fn move_to_recreated(entry: &mut JournalEntry, project_ids: &HashMap<Uuid, Uuid>) {
+    for secret in &mut entry.secrets {
+        if let Some(new_id) = secret.project_id.and_then(|id| project_ids.get(&id)) {
+            secret.project_id = Some(*new_id);
+        }
+    }
+    for secret in &mut entry.detached {
+        if let Some(new_id) = project_ids.get(&secret.project_id) {
+            secret.project_id = *new_id;
+        }
+    }
+}
+
+/// Moves a secret that was left without a project back into its recreated project.
+async fn reattach(
+    client: &Client,
+    organization_id: Uuid,
+    secret: &DetachedSecret,
+) -> Result<SecretResponse> {
+    let current = client
+        .secrets()
+        .get(&SecretGetRequest { id: secret.id })
+        .await?;
+
+    Ok(client
+        .secrets()
+        .update(&SecretPutRequest {
+            id: secret.id,
+            organization_id,
+            key: current.key,
+            value: current.value,
+            note: current.note,
+            project_ids: Some(vec![secret.project_id]),
+        })
+        .await?)
+}
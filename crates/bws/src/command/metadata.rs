@@ -0,0 +1,125 @@
+use bitwarden::{
+    secrets_manager::{
+        secrets::{SecretGetRequest, SecretPutRequest},
+        ClientSecretsExt,
+    },
+    Client,
+};
+use chrono::{NaiveDate, Utc};
+use color_eyre::eyre::Result;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    command::secret::{find_secrets, SecretFilter},
+    metadata::{format_note, parse_note},
+    render::{serialize_response, OutputSettings},
+    util::parse_duration,
+    TagCommand,
+};
+
+/// A secret with an expiry date in its note.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExpiringSecret {
+    pub(crate) id: Uuid,
+    pub(crate) key: String,
+    pub(crate) owner: Option<String>,
+    pub(crate) expires: NaiveDate,
+    /// Negative once the secret has expired.
+    pub(crate) days_left: i64,
+}
+
+pub(crate) async fn tag(
+    client: Client,
+    organization_id: Uuid,
+    command: TagCommand,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let (secret_id, tags, add) = match command {
+        TagCommand::Add { secret_id, tags } => (secret_id, tags, true),
+        TagCommand::Remove { secret_id, tags } => (secret_id, tags, false),
+    };
+
+    let secret = client
+        .secrets()
+        .get(&SecretGetRequest { id: secret_id })
+        .await?;
+
+    let (mut metadata, text) = parse_note(&secret.note)?;
+    for tag in tags {
+        match add {
+            true if !metadata.tags.contains(&tag) => metadata.tags.push(tag),
+            false => metadata.tags.retain(|t| *t != tag),
+            _ => (),
+        }
+    }
+    let note = format_note(&metadata, text)?;
+    if note == secret.note {
+        let mut secret = secret;
+        secret.value = output_settings.mask(secret.value);
+        serialize_response(secret, output_settings);
+        return Ok(());
+    }
+
+    let mut secret = client
+        .secrets()
+        .update(&SecretPutRequest {
+            id: secret.id,
+            organization_id,
+            key: secret.key,
+            value: secret.value,
+            note,
+            project_ids: secret.project_id.map(|id| vec![id]),
+        })
+        .await?;
+    audit::record(AuditAction::Update, [(secret.id, secret.key.clone())])?;
+    secret.value = output_settings.mask(secret.value);
+    serialize_response(secret, output_settings);
+
+    Ok(())
+}
+
+/// Lists the secrets expiring within a duration, including expired ones, soonest first. Secrets
+/// with invalid front-matter are skipped with a warning.
+pub(crate) async fn expiring(
+    client: Client,
+    organization_id: Uuid,
+    within: String,
+    project_id: Option<Uuid>,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let cutoff = Utc::now() + parse_duration(&within)?;
+    let today = Utc::now().date_naive();
+
+    let filter = SecretFilter {
+        project_id,
+        ..Default::default()
+    };
+    let mut expiring = Vec::new();
+    for secret in find_secrets(&client, organization_id, &filter).await? {
+        let metadata = match parse_note(&secret.note) {
+            Ok((metadata, _)) => metadata,
+            Err(e) => {
+                eprintln!("Warning: Skipping {} ({}): {e}", secret.key, secret.id);
+                continue;
+            }
+        };
+
+        if let Some(expires) = metadata.expires.filter(|e| *e <= cutoff.date_naive()) {
+            expiring.push(ExpiringSecret {
+                id: secret.id,
+                key: secret.key,
+                owner: metadata.owner,
+                expires,
+                days_left: (expires - today).num_days(),
+            });
+        }
+    }
+    expiring.sort_by_key(|s| s.expires);
+
+    serialize_response(expiring, output_settings);
+
+    Ok(())
+}
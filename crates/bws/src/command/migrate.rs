@@ -0,0 +1,235 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bitwarden::{
+    secrets_manager::{
+        projects::{ProjectCreateRequest, ProjectsListRequest},
+        secrets::{SecretCreateRequest, SecretIdentifiersRequest, SecretsGetRequest},
+        ClientProjectsExt, ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    confirm::Confirmation,
+    error::{CliError, ErrorCategory},
+    util::write_private_file,
+};
+
+pub(crate) struct MigrateCommandModel {
+    pub(crate) source: Client,
+    pub(crate) source_organization_id: Uuid,
+    pub(crate) target: Client,
+    pub(crate) target_organization_id: Uuid,
+    pub(crate) mapping_file: PathBuf,
+    pub(crate) dry_run: bool,
+}
+
+/// The IDs in the target organization of everything migrated so far, keyed by the source IDs.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IdMapping {
+    source_organization_id: Option<Uuid>,
+    target_organization_id: Option<Uuid>,
+    projects: BTreeMap<Uuid, Uuid>,
+    secrets: BTreeMap<Uuid, Uuid>,
+}
+
+impl IdMapping {
+    fn load(path: &Path) -> Result<IdMapping> {
+        if !path.exists() {
+            return Ok(IdMapping::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        write_private_file(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Recreates every project and secret of the source organization in the target organization.
+///
+/// The mapping file is written after each created item, so an interrupted migration can be resumed
+/// by running it again with the same file. Items already in the mapping are skipped.
+pub(crate) async fn migrate(
+    migrate: MigrateCommandModel,
+    confirmation: Confirmation,
+) -> Result<()> {
+    if migrate.source_organization_id == migrate.target_organization_id {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "Both profiles belong to the same organization"
+        ));
+    }
+
+    let mut mapping = IdMapping::load(&migrate.mapping_file)?;
+    match (
+        mapping.source_organization_id,
+        mapping.target_organization_id,
+    ) {
+        (None, None) => {
+            mapping.source_organization_id = Some(migrate.source_organization_id);
+            mapping.target_organization_id = Some(migrate.target_organization_id);
+        }
+        (Some(source), Some(target))
+            if source == migrate.source_organization_id
+                && target == migrate.target_organization_id => {}
+        _ => bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!(
+                "{} belongs to a migration between other organizations",
+                migrate.mapping_file.display()
+            )
+        )),
+    }
+
+    let projects: Vec<_> = migrate
+        .source
+        .projects()
+        .list(&ProjectsListRequest {
+            organization_id: migrate.source_organization_id,
+        })
+        .await?
+        .data
+        .into_iter()
+        .filter(|p| !mapping.projects.contains_key(&p.id))
+        .collect();
+
+    let secret_ids: Vec<Uuid> = migrate
+        .source
+        .secrets()
+        .list(&SecretIdentifiersRequest {
+            organization_id: migrate.source_organization_id,
+        })
+        .await?
+        .data
+        .into_iter()
+        .map(|s| s.id)
+        .filter(|id| !mapping.secrets.contains_key(id))
+        .collect();
+
+    let skipped = mapping.projects.len() + mapping.secrets.len();
+    if skipped > 0 {
+        println!("{skipped} items were already migrated and will be skipped.");
+    }
+
+    if migrate.dry_run {
+        println!(
+            "{} projects and {} secrets would be migrated.",
+            projects.len(),
+            secret_ids.len()
+        );
+        return Ok(());
+    }
+
+    if projects.is_empty() && secret_ids.is_empty() {
+        println!("Nothing to migrate.");
+        return Ok(());
+    }
+
+    confirmation.confirm(
+        &format!(
+            "Migrate {} projects and {} secrets?",
+            projects.len(),
+            secret_ids.len()
+        ),
+        &[],
+    )?;
+
+    for project in projects {
+        let new_project = migrate
+            .target
+            .projects()
+            .create(&ProjectCreateRequest {
+                organization_id: migrate.target_organization_id,
+                name: project.name,
+            })
+            .await?;
+        audit::record(AuditAction::Create, [(new_project.id, new_project.name)])?;
+
+        mapping.projects.insert(project.id, new_project.id);
+        mapping.save(&migrate.mapping_file)?;
+    }
+
+    let secrets = match secret_ids.is_empty() {
+        true => Vec::new(),
+        false => {
+            migrate
+                .source
+                .secrets()
+                .get_by_ids(SecretsGetRequest { ids: secret_ids })
+                .await?
+                .data
+        }
+    };
+
+    for secret in secrets {
+        let project_ids = match secret.project_id {
+            Some(project_id) => match mapping.projects.get(&project_id) {
+                Some(new_project_id) => Some(vec![*new_project_id]),
+                None => bail!(CliError::new(
+                    ErrorCategory::NotFound,
+                    format!(
+                        "Project {project_id} of secret {} wasn't migrated",
+                        secret.id
+                    )
+                )),
+            },
+            None => None,
+        };
+
+        let new_secret = migrate
+            .target
+            .secrets()
+            .create(&SecretCreateRequest {
+                organization_id: migrate.target_organization_id,
+                key: secret.key,
+                value: secret.value,
+                note: secret.note,
+                project_ids,
+            })
+            .await?;
+        audit::record(AuditAction::Create, [(new_secret.id, new_secret.key)])?;
+
+        mapping.secrets.insert(secret.id, new_secret.id);
+        mapping.save(&migrate.mapping_file)?;
+    }
+
+    println!(
+        "Migration complete. The ID mapping was written to {}.",
+        migrate.mapping_file.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn mapping_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mapping.json");
+        assert_eq!(IdMapping::default(), IdMapping::load(&path).unwrap());
+
+        let mut mapping = IdMapping::default();
+        mapping.projects.insert(Uuid::new_v4(), Uuid::new_v4());
+        mapping.secrets.insert(Uuid::new_v4(), Uuid::new_v4());
+        mapping.save(&path).unwrap();
+
+        assert_eq!(mapping, IdMapping::load(&path).unwrap());
+    }
+}
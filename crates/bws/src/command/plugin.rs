@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process,
+};
+
+use color_eyre::eyre::{bail, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    agent::{Agent, AGENT_SOCKET_VAR_NAME},
+    error::{CliError, ErrorCategory},
+    render::{serialize_response, OutputSettings},
+    PluginsCommand, ACCESS_TOKEN_KEY_VAR_NAME, PROFILE_KEY_VAR_NAME, SERVER_URL_KEY_VAR_NAME,
+};
+
+/// Plugins are executables named `bws-<name>` on PATH, run as `bws <name>`.
+const PLUGIN_PREFIX: &str = "bws-";
+
+#[derive(Debug, Serialize)]
+pub(crate) struct PluginInfo {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+}
+
+/// What a plugin is told about the session of the `bws` that runs it.
+#[derive(Debug)]
+pub(crate) struct PluginContext {
+    pub(crate) access_token: String,
+    pub(crate) organization_id: Uuid,
+    pub(crate) api_url: String,
+    pub(crate) identity_url: String,
+    pub(crate) profile: Option<String>,
+    pub(crate) server_url: Option<String>,
+}
+
+pub(crate) fn process_command(
+    command: PluginsCommand,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    match command {
+        PluginsCommand::List => {
+            serialize_response(find_plugins(), output_settings);
+            Ok(())
+        }
+    }
+}
+
+/// The plugins on PATH, by name. When several have the same name, the first on PATH is used.
+pub(crate) fn find_plugins() -> Vec<PluginInfo> {
+    let mut plugins = BTreeMap::new();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let Some(name) = plugin_name(&path) else {
+                continue;
+            };
+            if is_executable(&path) {
+                plugins.entry(name).or_insert(path);
+            }
+        }
+    }
+
+    plugins
+        .into_iter()
+        .map(|(name, path)| PluginInfo { name, path })
+        .collect()
+}
+
+/// Finds the plugin for an unknown subcommand, failing like clap does for a mistyped one.
+pub(crate) fn find_plugin(name: &str) -> Result<PathBuf> {
+    match which::which(format!("{PLUGIN_PREFIX}{name}")) {
+        Ok(path) => Ok(path),
+        Err(_) => bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!(
+                "Unrecognized subcommand '{name}', and no {PLUGIN_PREFIX}{name} plugin was found on PATH. Run `bws --help` for the commands, or `bws plugins list` for the plugins."
+            )
+        )),
+    }
+}
+
+/// Runs a plugin with the rest of the arguments, returning its exit code. Besides the server URLs
+/// and organization ID, it gets the path of a socket serving the access token, which stops
+/// serving once the plugin exits. `bws` run by the plugin reads the access token from there, so
+/// plugins are only supported on Unix.
+pub(crate) fn run(args: Vec<String>, context: PluginContext) -> Result<i32> {
+    let path = find_plugin(&args[0])?;
+    let agent = Agent::start(&context.access_token)?;
+
+    let mut command = process::Command::new(&path);
+    command
+        .args(&args[1..])
+        .env_remove(ACCESS_TOKEN_KEY_VAR_NAME)
+        .env("BWS_ORGANIZATION_ID", context.organization_id.to_string())
+        .env("BWS_API_URL", context.api_url)
+        .env("BWS_IDENTITY_URL", context.identity_url);
+    command.env(AGENT_SOCKET_VAR_NAME, &agent.socket);
+    // Plugins running bws should use the same profile and server
+    if let Some(profile) = context.profile {
+        command.env(PROFILE_KEY_VAR_NAME, profile);
+    }
+    if let Some(server_url) = context.server_url {
+        command.env(SERVER_URL_KEY_VAR_NAME, server_url);
+    }
+
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(e) => bail!("Failed to execute {}: {e}", path.display()),
+    };
+
+    Ok(status.code().unwrap_or(1))
+}
+
+fn plugin_name(path: &Path) -> Option<String> {
+    let name = match cfg!(windows) {
+        true => path.file_stem()?,
+        false => path.file_name()?,
+    };
+    let name = name.to_str()?.strip_prefix(PLUGIN_PREFIX)?;
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_names() {
+        assert_eq!(
+            Some("deploy".to_string()),
+            plugin_name(Path::new("/usr/local/bin/bws-deploy"))
+        );
+        assert_eq!(None, plugin_name(Path::new("/usr/local/bin/bws-")));
+        assert_eq!(None, plugin_name(Path::new("/usr/local/bin/bws")));
+        assert_eq!(
+            None,
+            plugin_name(Path::new("/usr/local/bin/git-bws-deploy"))
+        );
+    }
+}
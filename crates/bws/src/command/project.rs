@@ -4,14 +4,22 @@ use bitwarden::{
             ProjectCreateRequest, ProjectGetRequest, ProjectPutRequest, ProjectsDeleteRequest,
             ProjectsListRequest,
         },
-        ClientProjectsExt,
+        secrets::SecretIdentifiersByProjectRequest,
+        ClientProjectsExt, ClientSecretsExt,
     },
     Client,
 };
-use color_eyre::eyre::{bail, Result};
+use color_eyre::eyre::Result;
 use uuid::Uuid;
 
 use crate::{
+    audit::{self, AuditAction},
+    backup::BackupProject,
+    command::copy::{self, ProjectCopyCommandModel},
+    completion,
+    confirm::Confirmation,
+    error::report_partial_failure,
+    journal::{DetachedSecret, Journal},
     render::{serialize_response, OutputSettings},
     ProjectCommand,
 };
@@ -21,6 +29,8 @@ pub(crate) async fn process_command(
     client: Client,
     organization_id: Uuid,
     output_settings: OutputSettings,
+    confirmation: Confirmation,
+    journal: Option<Journal>,
 ) -> Result<()> {
     match command {
         ProjectCommand::List => list(client, organization_id, output_settings).await,
@@ -29,9 +39,38 @@ pub(crate) async fn process_command(
             create(client, organization_id, name, output_settings).await
         }
         ProjectCommand::Edit { project_id, name } => {
+            confirmation.confirm_protected()?;
             edit(client, organization_id, project_id, name, output_settings).await
         }
-        ProjectCommand::Delete { project_ids } => delete(client, project_ids).await,
+        ProjectCommand::Copy {
+            from,
+            to,
+            create_target,
+            keys,
+            mode,
+            placeholder,
+            dry_run,
+        } => {
+            copy::copy(
+                client,
+                organization_id,
+                ProjectCopyCommandModel {
+                    from,
+                    to,
+                    create_target,
+                    keys,
+                    mode,
+                    placeholder,
+                    dry_run,
+                },
+                output_settings,
+                confirmation,
+            )
+            .await
+        }
+        ProjectCommand::Delete { project_ids } => {
+            delete(client, organization_id, project_ids, confirmation, journal).await
+        }
     }
 }
 
@@ -45,6 +84,7 @@ pub(crate) async fn list(
         .list(&ProjectsListRequest { organization_id })
         .await?
         .data;
+    completion::cache_projects(projects.iter().map(|p| (p.id, p.name.clone())));
     serialize_response(projects, output_settings);
 
     Ok(())
@@ -77,6 +117,7 @@ pub(crate) async fn create(
             name,
         })
         .await?;
+    audit::record(AuditAction::Create, [(project.id, project.name.clone())])?;
     serialize_response(project, output_settings);
 
     Ok(())
@@ -97,18 +138,66 @@ pub(crate) async fn edit(
             name,
         })
         .await?;
+    audit::record(AuditAction::Update, [(project.id, project.name.clone())])?;
     serialize_response(project, output_settings);
 
     Ok(())
 }
 
-pub(crate) async fn delete(client: Client, project_ids: Vec<Uuid>) -> Result<()> {
+pub(crate) async fn delete(
+    client: Client,
+    organization_id: Uuid,
+    project_ids: Vec<Uuid>,
+    confirmation: Confirmation,
+    journal: Option<Journal>,
+) -> Result<()> {
     let count = project_ids.len();
 
-    let result = client
+    // Without a journal, the names are only needed when prompting, so automation with --yes skips
+    // the lookup
+    let names = match confirmation.yes && journal.is_none() {
+        true => Vec::new(),
+        false => project_names(&client, &project_ids).await,
+    };
+    let prompt_names: Vec<(Uuid, String)> = names
+        .iter()
+        .map(|(id, name)| {
+            (
+                *id,
+                name.clone().unwrap_or_else(|| "<not found>".to_string()),
+            )
+        })
+        .collect();
+    confirmation.confirm(&format!("Delete {count} projects?"), &prompt_names)?;
+
+    let entry_id = match &journal {
+        Some(journal) => {
+            let projects: Vec<BackupProject> = names
+                .into_iter()
+                .filter_map(|(id, name)| name.map(|name| BackupProject { id, name }))
+                .collect();
+            let detached = project_secrets(&client, &projects).await?;
+            Some(journal.record(organization_id, projects, Vec::new(), detached)?)
+        }
+        None => None,
+    };
+
+    let result = match client
         .projects()
-        .delete(ProjectsDeleteRequest { ids: project_ids })
-        .await?;
+        .delete(ProjectsDeleteRequest {
+            ids: project_ids.clone(),
+        })
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            // Nothing was deleted, so there's nothing to undo
+            if let (Some(journal), Some(entry_id)) = (&journal, entry_id) {
+                journal.remove(entry_id)?;
+            }
+            return Err(e.into());
+        }
+    };
 
     let projects_failed: Vec<(Uuid, String)> = result
         .data
@@ -116,6 +205,21 @@ pub(crate) async fn delete(client: Client, project_ids: Vec<Uuid>) -> Result<()>
         .filter_map(|r| r.error.map(|e| (r.id, e)))
         .collect();
     let deleted_projects = count - projects_failed.len();
+    let deleted_ids: Vec<Uuid> = project_ids
+        .into_iter()
+        .filter(|id| !projects_failed.iter().any(|(failed, _)| failed == id))
+        .collect();
+
+    audit::record(
+        AuditAction::Delete,
+        deleted_ids.iter().map(|id| {
+            let name = prompt_names
+                .iter()
+                .find(|(n, _)| n == id)
+                .map(|(_, name)| name);
+            (*id, name.cloned().unwrap_or_default())
+        }),
+    )?;
 
     match deleted_projects {
         2.. => println!("{} projects deleted successfully.", deleted_projects),
@@ -123,19 +227,55 @@ pub(crate) async fn delete(client: Client, project_ids: Vec<Uuid>) -> Result<()>
         _ => (),
     }
 
-    match projects_failed.len() {
-        2.. => eprintln!("{} projects had errors:", projects_failed.len()),
-        1 => eprintln!("{} project had an error:", projects_failed.len()),
-        _ => (),
+    if let (Some(journal), Some(entry_id)) = (&journal, entry_id) {
+        if journal.reconcile(entry_id, &deleted_ids)? {
+            eprintln!("Run `bws undo --id {entry_id}` to recreate them.");
+        }
     }
 
-    for project in &projects_failed {
-        eprintln!("{}: {}", project.0, project.1);
+    report_partial_failure(
+        "project",
+        "Errors when attempting to delete projects.",
+        &projects_failed,
+    )?;
+
+    Ok(())
+}
+
+/// Lists the secrets of the given projects, which are left without a project when it's deleted.
+async fn project_secrets(
+    client: &Client,
+    projects: &[BackupProject],
+) -> Result<Vec<DetachedSecret>> {
+    let mut secrets = Vec::new();
+    for project in projects {
+        let identifiers = client
+            .secrets()
+            .list_by_project(&SecretIdentifiersByProjectRequest {
+                project_id: project.id,
+            })
+            .await?;
+        secrets.extend(identifiers.data.into_iter().map(|s| DetachedSecret {
+            id: s.id,
+            project_id: project.id,
+        }));
     }
 
-    if !projects_failed.is_empty() {
-        bail!("Errors when attempting to delete projects.");
+    Ok(secrets)
+}
+
+/// Looks up the names of the given projects, which are `None` for projects that can't be found.
+async fn project_names(client: &Client, project_ids: &[Uuid]) -> Vec<(Uuid, Option<String>)> {
+    let mut names = Vec::new();
+    for id in project_ids {
+        let name = client
+            .projects()
+            .get(&ProjectGetRequest { id: *id })
+            .await
+            .ok()
+            .map(|project| project.name);
+        names.push((*id, name));
     }
 
-    Ok(())
+    names
 }
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use bitwarden::{
+    secrets_manager::{
+        secrets::{SecretIdentifiersByProjectRequest, SecretPutRequest, SecretsGetRequest},
+        ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{bail, Result};
+use regex::Regex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    confirm::Confirmation,
+    error::{report_partial_failure, CliError, ErrorCategory},
+    render::{serialize_response, OutputSettings},
+    util,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum KeyCase {
+    UpperSnake,
+    LowerSnake,
+}
+
+impl KeyCase {
+    fn apply(self, key: &str) -> String {
+        match self {
+            KeyCase::UpperSnake => util::to_snake_case(key).to_uppercase(),
+            KeyCase::LowerSnake => util::to_snake_case(key),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SecretRenameCommandModel {
+    pub(crate) project_id: Uuid,
+    pub(crate) pattern: Option<String>,
+    pub(crate) replace: Option<String>,
+    pub(crate) case: Option<KeyCase>,
+    pub(crate) dry_run: bool,
+}
+
+/// A single key change, as shown in the preview of `bws secret rename`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SecretRename {
+    pub(crate) id: Uuid,
+    pub(crate) key: String,
+    pub(crate) new_key: String,
+}
+
+struct RenameRule {
+    pattern: Option<Regex>,
+    replace: Option<String>,
+    case: Option<KeyCase>,
+}
+
+impl RenameRule {
+    /// Returns the new key, or `None` if the key doesn't match or stays the same.
+    fn apply(&self, key: &str) -> Option<String> {
+        let mut new_key = match (&self.pattern, &self.replace) {
+            (Some(pattern), _) if !pattern.is_match(key) => return None,
+            (Some(pattern), Some(replace)) => pattern.replace_all(key, replace).into_owned(),
+            _ => key.to_string(),
+        };
+
+        if let Some(case) = self.case {
+            new_key = case.apply(&new_key);
+        }
+
+        (new_key != key).then_some(new_key)
+    }
+}
+
+pub(crate) async fn rename(
+    client: Client,
+    organization_id: Uuid,
+    rename: SecretRenameCommandModel,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+) -> Result<()> {
+    let rule = RenameRule {
+        pattern: rename.pattern.as_deref().map(Regex::new).transpose()?,
+        replace: rename.replace,
+        case: rename.case,
+    };
+
+    // Keys only have to be unique within a project, so that's where collisions are checked
+    let identifiers = client
+        .secrets()
+        .list_by_project(&SecretIdentifiersByProjectRequest {
+            project_id: rename.project_id,
+        })
+        .await?;
+    let keys: Vec<(Uuid, String)> = identifiers
+        .data
+        .into_iter()
+        .map(|s| (s.id, s.key))
+        .collect();
+
+    let renames = plan_renames(&keys, &rule)?;
+    if renames.is_empty() {
+        eprintln!("No secrets matched.");
+        return Ok(());
+    }
+
+    serialize_response(renames.clone(), output_settings);
+    if rename.dry_run {
+        return Ok(());
+    }
+
+    confirmation.confirm(&format!("Rename {} secrets?", renames.len()), &[])?;
+
+    let new_keys: HashMap<Uuid, String> = renames.into_iter().map(|r| (r.id, r.new_key)).collect();
+    let secrets = client
+        .secrets()
+        .get_by_ids(SecretsGetRequest {
+            ids: new_keys.keys().copied().collect(),
+        })
+        .await?
+        .data;
+
+    let mut renamed = Vec::new();
+    let mut secrets_failed = Vec::new();
+    for secret in secrets {
+        let Some(new_key) = new_keys.get(&secret.id) else {
+            continue;
+        };
+
+        // Only the key changes, the value, note and project are written back as they are
+        let result = client
+            .secrets()
+            .update(&SecretPutRequest {
+                id: secret.id,
+                organization_id,
+                key: new_key.clone(),
+                value: secret.value,
+                note: secret.note,
+                project_ids: secret.project_id.map(|id| vec![id]),
+            })
+            .await;
+
+        match result {
+            Ok(secret) => renamed.push((secret.id, secret.key)),
+            Err(e) => secrets_failed.push((secret.id, e.to_string())),
+        }
+    }
+
+    let count = renamed.len();
+    audit::record(AuditAction::Update, renamed)?;
+
+    match count {
+        2.. => println!("{} secrets renamed successfully.", count),
+        1 => println!("{} secret renamed successfully.", count),
+        _ => (),
+    }
+
+    report_partial_failure(
+        "secret",
+        "Errors when attempting to rename secrets.",
+        &secrets_failed,
+    )?;
+
+    Ok(())
+}
+
+/// Works out the new key of every secret, refusing the whole rename if two secrets would end up
+/// with the same key.
+fn plan_renames(keys: &[(Uuid, String)], rule: &RenameRule) -> Result<Vec<SecretRename>> {
+    let renames: Vec<SecretRename> = keys
+        .iter()
+        .filter_map(|(id, key)| {
+            rule.apply(key).map(|new_key| SecretRename {
+                id: *id,
+                key: key.clone(),
+                new_key,
+            })
+        })
+        .collect();
+
+    // Secrets that aren't renamed keep their key, so they can collide with a new key too
+    let mut final_keys: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (id, key) in keys {
+        match renames.iter().find(|r| r.id == *id) {
+            Some(r) => final_keys.entry(&r.new_key).or_default().push(&r.key),
+            None => final_keys.entry(key).or_default().push(key),
+        }
+    }
+
+    let mut collisions: Vec<String> = renames
+        .iter()
+        .filter(|r| final_keys[r.new_key.as_str()].len() > 1)
+        .map(|r| {
+            format!(
+                "{}: {}",
+                r.new_key,
+                final_keys[r.new_key.as_str()].join(", ")
+            )
+        })
+        .collect();
+    collisions.sort();
+    collisions.dedup();
+
+    if !collisions.is_empty() {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "Renaming would give multiple secrets the same key, nothing was changed."
+        )
+        .with_details(collisions));
+    }
+
+    Ok(renames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<(Uuid, String)> {
+        keys.iter()
+            .map(|k| (Uuid::new_v4(), k.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn renames_matching_keys() {
+        let rule = RenameRule {
+            pattern: Some(Regex::new("^db-(.*)$").unwrap()),
+            replace: Some("database-$1".to_string()),
+            case: Some(KeyCase::UpperSnake),
+        };
+
+        let renames = plan_renames(&keys(&["db-password", "api-key"]), &rule).unwrap();
+        assert_eq!(1, renames.len());
+        assert_eq!("db-password", renames[0].key);
+        assert_eq!("DATABASE_PASSWORD", renames[0].new_key);
+    }
+
+    #[test]
+    fn unchanged_keys_are_skipped() {
+        let rule = RenameRule {
+            pattern: None,
+            replace: None,
+            case: Some(KeyCase::UpperSnake),
+        };
+
+        let renames = plan_renames(&keys(&["DB_PASSWORD", "api-key"]), &rule).unwrap();
+        assert_eq!(1, renames.len());
+        assert_eq!("API_KEY", renames[0].new_key);
+    }
+
+    #[test]
+    fn collisions_are_refused() {
+        let rule = RenameRule {
+            pattern: None,
+            replace: None,
+            case: Some(KeyCase::UpperSnake),
+        };
+
+        assert!(plan_renames(&keys(&["db-password", "dbPassword"]), &rule).is_err());
+        assert!(plan_renames(&keys(&["db-password", "DB_PASSWORD"]), &rule).is_err());
+    }
+
+    #[test]
+    fn rename_is_limited_to_a_project() {
+        use clap::Parser;
+
+        let rename = |args: &[&str]| {
+            crate::Cli::try_parse_from(
+                ["bws", "secret", "rename", "--to-upper-snake"]
+                    .iter()
+                    .chain(args),
+            )
+        };
+        assert!(rename(&[]).is_err());
+        assert!(rename(&["--project-id", &Uuid::new_v4().to_string()]).is_ok());
+    }
+}
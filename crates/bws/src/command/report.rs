@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use bitwarden::{secrets_manager::secrets::SecretResponse, Client};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use color_eyre::eyre::Result;
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    command::secret::{find_secrets, SecretFilter},
+    error::ErrorCategory,
+    render::{serialize_response, OutputSettings},
+    util::{is_valid_posix_name, parse_duration},
+    Severity,
+};
+
+#[derive(Debug)]
+pub(crate) struct ReportCommandModel {
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) stale_after: String,
+    pub(crate) min_entropy: f64,
+    pub(crate) fail_on: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ReportCheck {
+    Stale,
+    DuplicateKey,
+    SharedKey,
+    ReusedValue,
+    NonPosixKey,
+    NoProject,
+    WeakValue,
+}
+
+impl ReportCheck {
+    fn severity(self) -> Severity {
+        match self {
+            ReportCheck::DuplicateKey | ReportCheck::WeakValue => Severity::High,
+            ReportCheck::SharedKey | ReportCheck::Stale | ReportCheck::ReusedValue => {
+                Severity::Medium
+            }
+            ReportCheck::NonPosixKey | ReportCheck::NoProject => Severity::Low,
+        }
+    }
+}
+
+impl std::fmt::Display for ReportCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let check = match self {
+            ReportCheck::Stale => "stale",
+            ReportCheck::DuplicateKey => "duplicate_key",
+            ReportCheck::SharedKey => "shared_key",
+            ReportCheck::ReusedValue => "reused_value",
+            ReportCheck::NonPosixKey => "non_posix_key",
+            ReportCheck::NoProject => "no_project",
+            ReportCheck::WeakValue => "weak_value",
+        };
+        write!(f, "{check}")
+    }
+}
+
+/// A problem with a secret. Values are never included.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReportFinding {
+    #[serde(serialize_with = "serialize_severity")]
+    pub(crate) severity: Severity,
+    pub(crate) check: ReportCheck,
+    pub(crate) secret_id: Uuid,
+    pub(crate) key: String,
+    pub(crate) detail: String,
+}
+
+/// The name of a severity, as it's given to `--fail-on`.
+pub(crate) fn severity_name(severity: Severity) -> String {
+    severity
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn serialize_severity<S: Serializer>(
+    severity: &Severity,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&severity_name(*severity))
+}
+
+/// Returns the exit code, which is the findings code when a finding has at least the `--fail-on`
+/// severity.
+pub(crate) async fn report(
+    client: Client,
+    organization_id: Uuid,
+    report: ReportCommandModel,
+    output_settings: OutputSettings,
+) -> Result<i32> {
+    let stale_after = parse_duration(&report.stale_after)?;
+    let filter = SecretFilter {
+        project_id: report.project_id,
+        ..Default::default()
+    };
+    let secrets = find_secrets(&client, organization_id, &filter).await?;
+
+    let findings = analyze(&secrets, Utc::now() - stale_after, report.min_entropy);
+    let exit_code = match findings.iter().any(|f| f.severity >= report.fail_on) {
+        true => ErrorCategory::Findings.exit_code(),
+        false => 0,
+    };
+    serialize_response(findings, output_settings);
+
+    Ok(exit_code)
+}
+
+/// Checks every secret, returning the findings with the most severe first.
+pub(crate) fn analyze(
+    secrets: &[SecretResponse],
+    stale_before: DateTime<Utc>,
+    min_entropy: f64,
+) -> Vec<ReportFinding> {
+    // Values are grouped by their hash, so no other copies of the plaintext are kept
+    let mut by_project_key: HashMap<(Uuid, &str), usize> = HashMap::new();
+    let mut by_key: HashMap<&str, usize> = HashMap::new();
+    let mut by_value: HashMap<[u8; 32], Vec<&str>> = HashMap::new();
+    for secret in secrets {
+        *by_key.entry(&secret.key).or_default() += 1;
+        if let Some(project_id) = secret.project_id {
+            *by_project_key.entry((project_id, &secret.key)).or_default() += 1;
+        }
+        if !secret.value.is_empty() {
+            by_value
+                .entry(Sha256::digest(&secret.value).into())
+                .or_default()
+                .push(&secret.key);
+        }
+    }
+
+    let mut findings = Vec::new();
+    for secret in secrets {
+        let mut add = |check: ReportCheck, detail: String| {
+            findings.push(ReportFinding {
+                severity: check.severity(),
+                check,
+                secret_id: secret.id,
+                key: secret.key.clone(),
+                detail,
+            })
+        };
+
+        if secret.revision_date < stale_before {
+            let days = (Utc::now() - secret.revision_date).num_days();
+            add(ReportCheck::Stale, format!("Not revised in {days} days"));
+        }
+
+        // `bws run` fails for a project when it has the key twice, and without --project-id when
+        // the organization has it twice
+        let project_count = secret.project_id.map(|project_id| {
+            (
+                project_id,
+                by_project_key[&(project_id, secret.key.as_str())],
+            )
+        });
+        let count = by_key[secret.key.as_str()];
+        match project_count {
+            Some((project_id, project_count)) if project_count > 1 => add(
+                ReportCheck::DuplicateKey,
+                format!("{project_count} secrets in project {project_id} have this key, so `bws run` fails"),
+            ),
+            _ if count > 1 => add(
+                ReportCheck::SharedKey,
+                format!("{count} secrets have this key, so `bws run` fails without --project-id"),
+            ),
+            _ => (),
+        }
+
+        if !secret.value.is_empty() {
+            let others: Vec<&str> = by_value[&<[u8; 32]>::from(Sha256::digest(&secret.value))]
+                .iter()
+                .filter(|key| **key != secret.key)
+                .copied()
+                .collect();
+            if !others.is_empty() {
+                add(
+                    ReportCheck::ReusedValue,
+                    format!("Same value as {}", others.join(", ")),
+                );
+            }
+        }
+
+        if !is_valid_posix_name(&secret.key) {
+            add(
+                ReportCheck::NonPosixKey,
+                "Not a valid environment variable name for `bws run`".to_string(),
+            );
+        }
+
+        if secret.project_id.is_none() {
+            add(ReportCheck::NoProject, "Not in any project".to_string());
+        }
+
+        let entropy = estimate_entropy(&secret.value);
+        if entropy < min_entropy {
+            add(
+                ReportCheck::WeakValue,
+                format!("Estimated entropy of {entropy:.0} bits"),
+            );
+        }
+    }
+
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    findings
+}
+
+/// Estimates the entropy of a value in bits, from the Shannon entropy of its characters times its
+/// length. This underestimates long random values but can't be fooled by a large alphabet alone.
+pub(crate) fn estimate_entropy(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+
+    let length = value.chars().count() as f64;
+    let per_char: f64 = counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / length;
+            -p * p.log2()
+        })
+        .sum();
+
+    per_char * length
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::test_util::secret;
+
+    fn checks(findings: &[ReportFinding], key: &str) -> Vec<ReportCheck> {
+        findings
+            .iter()
+            .filter(|f| f.key == key)
+            .map(|f| f.check)
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_check() {
+        let project = Some(Uuid::new_v4());
+        let secrets = vec![
+            secret("GOOD_KEY", "x7#Kq9!mZ2@vL4$wP8&nR", project),
+            SecretResponse {
+                revision_date: Utc::now() - TimeDelta::days(200),
+                ..secret("OLD_KEY", "h3$Jd8^sL1*qW6(eT0)yU", project)
+            },
+            secret("DUP", "a9!Fk2@Lm5#Qz8$Wx1%Er", project),
+            secret("DUP", "b8@Gj3#Nn6$Ry9%Vc2^Ts", project),
+            secret("SHARED", "f4^Kn7&Ss0*Vx3(Eg6)Xj", project),
+            secret("SHARED", "g3&Lo8*Tt1(Wy4)Fh7!Yk", Some(Uuid::new_v4())),
+            secret("REUSED_A", "c7#Hk4$Pp7%Su0^Bd3&Ug", project),
+            secret("REUSED_B", "c7#Hk4$Pp7%Su0^Bd3&Ug", project),
+            secret("bad-name", "d6$Il5%Qq8^Tv1&Ce4*Vh", project),
+            secret("LOOSE", "e5%Jm6^Rr9&Uw2*Df5(Wi", None),
+            secret("WEAK", "password", project),
+        ];
+
+        let findings = analyze(&secrets, Utc::now() - TimeDelta::days(90), 40.0);
+
+        assert!(checks(&findings, "GOOD_KEY").is_empty());
+        assert_eq!(vec![ReportCheck::Stale], checks(&findings, "OLD_KEY"));
+        assert_eq!(
+            vec![ReportCheck::DuplicateKey, ReportCheck::DuplicateKey],
+            checks(&findings, "DUP")
+        );
+        assert_eq!(
+            vec![ReportCheck::SharedKey, ReportCheck::SharedKey],
+            checks(&findings, "SHARED")
+        );
+        assert_eq!(
+            vec![ReportCheck::ReusedValue],
+            checks(&findings, "REUSED_A")
+        );
+        assert_eq!(
+            vec![ReportCheck::NonPosixKey],
+            checks(&findings, "bad-name")
+        );
+        assert_eq!(vec![ReportCheck::NoProject], checks(&findings, "LOOSE"));
+        assert_eq!(vec![ReportCheck::WeakValue], checks(&findings, "WEAK"));
+        assert_eq!(Severity::High, findings[0].severity);
+    }
+
+    #[test]
+    fn entropy_estimate() {
+        assert_eq!(0.0, estimate_entropy(""));
+        assert_eq!(0.0, estimate_entropy("aaaa"));
+        assert_eq!(8.0, estimate_entropy("abcd"));
+        assert!(estimate_entropy("password") < 40.0);
+        assert!(estimate_entropy("x7#Kq9!mZ2@vL4$wP8&nR") > 40.0);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     collections::HashMap,
     io::{IsTerminal, Read},
+    path::PathBuf,
     process,
 };
 
@@ -11,28 +12,54 @@ use bitwarden::{
     },
     Client,
 };
+use chrono::TimeDelta;
 use color_eyre::eyre::{bail, Result};
 use itertools::Itertools;
 use uuid::Uuid;
 use which::which;
 
 use crate::{
-    util::{is_valid_posix_name, uuid_to_posix},
+    metadata::has_tags,
+    procfile,
+    util::{is_valid_posix_name, uuid_to_posix, with_timeout},
     ACCESS_TOKEN_KEY_VAR_NAME,
 };
 
 // Essential environment variables that should be preserved even when `--no-inherit-env` is used
 const WINDOWS_ESSENTIAL_VARS: &[&str] = &["SystemRoot", "ComSpec", "windir"];
 
+#[derive(Debug)]
+pub(crate) struct RunCommandModel {
+    pub(crate) project_id: Option<Uuid>,
+    /// Only the secrets with every one of these tags are injected.
+    pub(crate) tags: Vec<String>,
+    pub(crate) uuids_as_keynames: bool,
+    pub(crate) no_inherit_env: bool,
+    pub(crate) shell: Option<String>,
+    pub(crate) command: Vec<String>,
+    /// Runs the processes of this Procfile instead of the command.
+    pub(crate) procfile: Option<PathBuf>,
+    /// Whether the names in front of the output of the Procfile processes are colored.
+    pub(crate) color: bool,
+}
+
 pub(crate) async fn run(
     client: Client,
     organization_id: Uuid,
-    project_id: Option<Uuid>,
-    uuids_as_keynames: bool,
-    no_inherit_env: bool,
-    shell: Option<String>,
-    command: Vec<String>,
+    timeout: Option<TimeDelta>,
+    run: RunCommandModel,
 ) -> Result<i32> {
+    let RunCommandModel {
+        project_id,
+        tags,
+        uuids_as_keynames,
+        no_inherit_env,
+        shell,
+        command,
+        procfile,
+        color,
+    } = run;
+
     let is_windows = std::env::consts::OS == "windows";
 
     let shell = shell.unwrap_or_else(|| {
@@ -47,7 +74,17 @@ pub(crate) async fn run(
         bail!("Shell '{}' not found", shell);
     }
 
-    let user_command = if command.is_empty() {
+    let procfile = match procfile {
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(text) => Some(procfile::parse(&text)?),
+            Err(e) => bail!("Failed to read {}: {e}", path.display()),
+        },
+        None => None,
+    };
+
+    let user_command = if procfile.is_some() {
+        String::new()
+    } else if command.is_empty() {
         if std::io::stdin().is_terminal() {
             bail!("No command provided");
         }
@@ -59,24 +96,85 @@ pub(crate) async fn run(
         command.join(" ")
     };
 
-    let res = if let Some(project_id) = project_id {
-        client
-            .secrets()
-            .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
-            .await?
-    } else {
+    let environment = fetch_environment(
+        &client,
+        organization_id,
+        timeout,
+        project_id,
+        &tags,
+        uuids_as_keynames,
+    )
+    .await?;
+    for key in environment.keys() {
+        if !is_valid_posix_name(key) {
+            eprintln!(
+                "Warning: secret '{}' does not have a POSIX-compliant name",
+                key
+            );
+        }
+    }
+
+    if let Some(entries) = procfile {
+        let commands = entries
+            .iter()
+            .map(|e| build_command(&shell, &e.command, &environment, no_inherit_env))
+            .collect();
+        return procfile::run(entries, commands, color).await;
+    }
+
+    let mut command = build_command(&shell, &user_command, &environment, no_inherit_env);
+    command
+        .stdout(process::Stdio::inherit())
+        .stderr(process::Stdio::inherit());
+
+    // propagate the exit status from the child process
+    match command.spawn() {
+        Ok(mut child) => match child.wait() {
+            Ok(exit_status) => Ok(exit_status.code().unwrap_or(1)),
+            Err(e) => {
+                bail!("Failed to wait for process: {}", e)
+            }
+        },
+        Err(e) => {
+            bail!("Failed to execute process: {}", e)
+        }
+    }
+}
+
+/// Fetches the secrets of the project, or of the whole organization, that have every one of the
+/// tags, named by their key or by their UUID in its POSIX form.
+pub(crate) async fn fetch_environment(
+    client: &Client,
+    organization_id: Uuid,
+    timeout: Option<TimeDelta>,
+    project_id: Option<Uuid>,
+    tags: &[String],
+    uuids_as_keynames: bool,
+) -> Result<HashMap<String, String>> {
+    let secrets = with_timeout(timeout, async {
+        let res = if let Some(project_id) = project_id {
+            client
+                .secrets()
+                .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
+                .await?
+        } else {
+            client
+                .secrets()
+                .list(&SecretIdentifiersRequest { organization_id })
+                .await?
+        };
+
+        let secret_ids = res.data.into_iter().map(|e| e.id).collect();
         client
             .secrets()
-            .list(&SecretIdentifiersRequest { organization_id })
-            .await?
-    };
-
-    let secret_ids = res.data.into_iter().map(|e| e.id).collect();
-    let secrets = client
-        .secrets()
-        .get_by_ids(SecretsGetRequest { ids: secret_ids })
-        .await?
-        .data;
+            .get_by_ids(SecretsGetRequest { ids: secret_ids })
+            .await
+    })
+    .await??
+    .data
+    .into_iter()
+    .filter(|s| has_tags(&s.note, tags))
+    .collect::<Vec<_>>();
 
     if !uuids_as_keynames {
         if let Some(duplicate) = secrets.iter().map(|s| &s.key).duplicates().next() {
@@ -84,7 +182,7 @@ pub(crate) async fn run(
         }
     }
 
-    let environment: HashMap<String, String> = secrets
+    let environment = secrets
         .into_iter()
         .map(|s| {
             if uuids_as_keynames {
@@ -93,22 +191,22 @@ pub(crate) async fn run(
                 (s.key, s.value)
             }
         })
-        .inspect(|(k, _)| {
-            if !is_valid_posix_name(k) {
-                eprintln!(
-                    "Warning: secret '{}' does not have a POSIX-compliant name",
-                    k
-                );
-            }
-        })
         .collect();
 
+    Ok(environment)
+}
+
+/// Prepares the shell to run a command line with the secrets in its environment.
+fn build_command(
+    shell: &str,
+    user_command: &str,
+    environment: &HashMap<String, String>,
+    no_inherit_env: bool,
+) -> process::Command {
+    let is_windows = std::env::consts::OS == "windows";
+
     let mut command = process::Command::new(shell);
-    command
-        .arg("-c")
-        .arg(&user_command)
-        .stdout(process::Stdio::inherit())
-        .stderr(process::Stdio::inherit());
+    command.arg("-c").arg(user_command);
 
     if no_inherit_env {
         let path = std::env::var("PATH").unwrap_or_else(|_| match is_windows {
@@ -134,16 +232,5 @@ pub(crate) async fn run(
         command.envs(environment);
     }
 
-    // propagate the exit status from the child process
-    match command.spawn() {
-        Ok(mut child) => match child.wait() {
-            Ok(exit_status) => Ok(exit_status.code().unwrap_or(1)),
-            Err(e) => {
-                bail!("Failed to wait for process: {}", e)
-            }
-        },
-        Err(e) => {
-            bail!("Failed to execute process: {}", e)
-        }
-    }
+    command
 }
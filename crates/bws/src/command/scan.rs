@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use bitwarden::{secrets_manager::secrets::SecretResponse, Client};
+use color_eyre::eyre::{bail, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    command::secret::{find_secrets, SecretFilter},
+    error::{CliError, ErrorCategory},
+    render::{serialize_response, OutputSettings},
+};
+
+#[derive(Debug)]
+pub(crate) struct ScanCommandModel {
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) git_staged: bool,
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) min_length: usize,
+}
+
+/// A secret value found in a file. The value itself is never included.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ScanFinding {
+    pub(crate) file: String,
+    pub(crate) line: usize,
+    pub(crate) key: String,
+    pub(crate) secret_id: Uuid,
+}
+
+/// Searches for every secret value at once with an Aho-Corasick automaton. The automaton keeps its
+/// own copies of the values, so they stay in memory until the scan ends.
+pub(crate) struct Scanner {
+    automaton: AhoCorasick,
+    /// The secrets with the value of each pattern, as several secrets can share a value.
+    secrets: Vec<Vec<(Uuid, String)>>,
+}
+
+impl Scanner {
+    pub(crate) fn new(secrets: Vec<SecretResponse>, min_length: usize) -> Result<Scanner> {
+        let mut by_value: HashMap<String, Vec<(Uuid, String)>> = HashMap::new();
+        for secret in secrets {
+            // An empty value would match at every byte
+            if secret.value.is_empty() || secret.value.chars().count() < min_length {
+                continue;
+            }
+            by_value
+                .entry(secret.value)
+                .or_default()
+                .push((secret.id, secret.key));
+        }
+
+        let (values, secrets): (Vec<String>, Vec<_>) = by_value.into_iter().unzip();
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(values)?;
+
+        Ok(Scanner { automaton, secrets })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    pub(crate) fn scan(&self, file: &str, contents: &[u8]) -> Vec<ScanFinding> {
+        let mut findings = Vec::new();
+        let mut line = 1;
+        let mut position = 0;
+
+        for found in self.automaton.find_iter(contents) {
+            line += contents[position..found.start()]
+                .iter()
+                .filter(|b| **b == b'\n')
+                .count();
+            position = found.start();
+
+            for (secret_id, key) in &self.secrets[found.pattern().as_usize()] {
+                findings.push(ScanFinding {
+                    file: file.to_string(),
+                    line,
+                    key: key.clone(),
+                    secret_id: *secret_id,
+                });
+            }
+        }
+
+        findings
+    }
+}
+
+/// Returns the exit code, which is the findings code when a secret value was found.
+pub(crate) async fn scan(
+    client: Client,
+    organization_id: Uuid,
+    scan: ScanCommandModel,
+    output_settings: OutputSettings,
+) -> Result<i32> {
+    let filter = SecretFilter {
+        project_id: scan.project_id,
+        ..Default::default()
+    };
+    let scanner = Scanner::new(
+        find_secrets(&client, organization_id, &filter).await?,
+        scan.min_length,
+    )?;
+    if scanner.is_empty() {
+        eprintln!("Warning: No secret values are long enough to search for.");
+    }
+
+    let mut findings = Vec::new();
+    if scan.git_staged {
+        for file in staged_files()? {
+            findings.extend(scanner.scan(&file, &staged_contents(&file)?));
+        }
+    }
+    for path in &scan.paths {
+        if !path.exists() {
+            bail!(CliError::new(
+                ErrorCategory::NotFound,
+                format!("{} doesn't exist", path.display())
+            ));
+        }
+        scan_path(&scanner, path, &mut findings);
+    }
+
+    let count = findings.len();
+    serialize_response(findings, output_settings);
+
+    match count {
+        0 => Ok(0),
+        _ => {
+            eprintln!("Found {count} secret values.");
+            Ok(ErrorCategory::Findings.exit_code())
+        }
+    }
+}
+
+/// Searches a file, or every file in a directory except in `.git` directories. Symbolic links
+/// aren't followed, and files that can't be read are skipped with a warning.
+fn scan_path(scanner: &Scanner, path: &Path, findings: &mut Vec<ScanFinding>) {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("Warning: Skipping {}: {e}", path.display());
+            return;
+        }
+    };
+
+    if metadata.is_file() {
+        match std::fs::read(path) {
+            Ok(contents) => findings.extend(scanner.scan(&path.display().to_string(), &contents)),
+            Err(e) => eprintln!("Warning: Skipping {}: {e}", path.display()),
+        }
+    } else if metadata.is_dir() && path.file_name().map_or(true, |name| name != ".git") {
+        let mut entries: Vec<PathBuf> = match std::fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(e) => {
+                eprintln!("Warning: Skipping {}: {e}", path.display());
+                return;
+            }
+        };
+        entries.sort();
+
+        for entry in entries {
+            scan_path(scanner, &entry, findings);
+        }
+    }
+}
+
+/// The added, copied, modified and renamed files in the git index, relative to the repository
+/// root.
+fn staged_files() -> Result<Vec<String>> {
+    let output = git(&[
+        "diff",
+        "--cached",
+        "--name-only",
+        "--diff-filter=ACMR",
+        "-z",
+    ])?;
+
+    Ok(String::from_utf8_lossy(&output)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The contents of a file as it's staged, which can differ from the working tree.
+fn staged_contents(file: &str) -> Result<Vec<u8>> {
+    git(&["show", &format!(":{file}")])
+}
+
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    let output = match Command::new("git").args(args).output() {
+        Ok(output) => output,
+        Err(e) => bail!("Failed to run git: {e}"),
+    };
+
+    if !output.status.success() {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::secret;
+
+    #[test]
+    fn finds_values_by_line() {
+        let scanner = Scanner::new(
+            vec![
+                secret("DB_PASSWORD", "correct-horse-battery", None),
+                secret("SHORT", "abc", None),
+                secret("API_TOKEN", "tok_1234567890", None),
+                secret("API_TOKEN_COPY", "tok_1234567890", None),
+            ],
+            8,
+        )
+        .unwrap();
+
+        let contents =
+            b"host=db\npassword=correct-horse-battery\nshort=abc\n\ntoken: tok_1234567890\n";
+        let findings = scanner.scan("config.env", contents);
+
+        let found: Vec<(usize, &str)> = findings.iter().map(|f| (f.line, f.key.as_str())).collect();
+        assert_eq!(3, found.len());
+        assert_eq!((2, "DB_PASSWORD"), found[0]);
+        assert!(found.contains(&(5, "API_TOKEN")));
+        assert!(found.contains(&(5, "API_TOKEN_COPY")));
+    }
+
+    #[test]
+    fn no_values_finds_nothing() {
+        let scanner = Scanner::new(vec![secret("SHORT", "abc", None)], 8).unwrap();
+
+        assert!(scanner.is_empty());
+        assert!(scanner.scan("file", b"abc").is_empty());
+    }
+
+    #[test]
+    fn empty_values_are_skipped() {
+        use clap::Parser;
+
+        let scanner = Scanner::new(vec![secret("EMPTY", "", None)], 0).unwrap();
+        assert!(scanner.is_empty());
+
+        let scan = |min_length| {
+            crate::Cli::try_parse_from(["bws", "scan", ".", "--min-length", min_length])
+        };
+        assert!(scan("0").is_err());
+        assert!(scan("1").is_ok());
+    }
+}
@@ -2,18 +2,31 @@ use bitwarden::{
     secrets_manager::{
         secrets::{
             SecretCreateRequest, SecretGetRequest, SecretIdentifiersByProjectRequest,
-            SecretIdentifiersRequest, SecretPutRequest, SecretsDeleteRequest, SecretsGetRequest,
+            SecretIdentifiersRequest, SecretPutRequest, SecretResponse, SecretsDeleteRequest,
+            SecretsGetRequest,
         },
         ClientSecretsExt,
     },
     Client,
 };
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{bail, Result};
+use regex::Regex;
 use uuid::Uuid;
 
 use crate::{
+    audit::{self, AuditAction},
+    command::{
+        metadata,
+        rename::{self, KeyCase, SecretRenameCommandModel},
+    },
+    completion,
+    confirm::Confirmation,
+    error::{report_partial_failure, CliError, ErrorCategory},
+    journal::Journal,
+    metadata::has_tags,
     render::{serialize_response, OutputSettings},
-    SecretCommand,
+    util, SecretCommand,
 };
 
 #[derive(Debug)]
@@ -31,6 +44,89 @@ pub(crate) struct SecretEditCommandModel {
     pub(crate) value: Option<String>,
     pub(crate) note: Option<String>,
     pub(crate) project_id: Option<Uuid>,
+    /// Shows the secret as it would be after the edit, without changing it.
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct SecretBulkEditCommandModel {
+    pub(crate) filter: SecretFilter,
+    pub(crate) value: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct SecretDeleteCommandModel {
+    pub(crate) secret_ids: Vec<Uuid>,
+    pub(crate) filter: SecretFilter,
+    pub(crate) dry_run: bool,
+}
+
+/// Selects secrets for the bulk commands. A secret has to match every selector that is set.
+#[derive(Debug, Default)]
+pub(crate) struct SecretFilter {
+    pub(crate) project_id: Option<Uuid>,
+    /// A glob matched against the whole key.
+    pub(crate) key: Option<String>,
+    /// A duration such as `90d`, matching secrets that haven't been modified since.
+    pub(crate) older_than: Option<String>,
+}
+
+impl SecretFilter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.project_id.is_none() && self.key.is_none() && self.older_than.is_none()
+    }
+
+    /// Refuses a filter without any selector, so a bulk change can't reach every secret of the
+    /// organization by accident.
+    fn require_selector(&self) -> Result<()> {
+        if self.is_empty() {
+            bail!(CliError::new(
+                ErrorCategory::Validation,
+                "At least one selector is required, so that not every secret is changed"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn matcher(&self, now: DateTime<Utc>) -> Result<SecretMatcher> {
+        Ok(SecretMatcher {
+            project_id: self.project_id,
+            key: self.key.as_deref().map(util::glob_to_regex).transpose()?,
+            cutoff: self
+                .older_than
+                .as_deref()
+                .map(util::parse_duration)
+                .transpose()?
+                .map(|older_than| now - older_than),
+        })
+    }
+}
+
+/// A [`SecretFilter`] ready to be matched against secrets.
+struct SecretMatcher {
+    project_id: Option<Uuid>,
+    key: Option<Regex>,
+    /// Secrets revised at this time or later don't match.
+    cutoff: Option<DateTime<Utc>>,
+}
+
+impl SecretMatcher {
+    fn matches_key(&self, key: &str) -> bool {
+        self.key.as_ref().map_or(true, |regex| regex.is_match(key))
+    }
+
+    fn matches(&self, secret: &SecretResponse) -> bool {
+        self.project_id
+            .map_or(true, |project_id| secret.project_id == Some(project_id))
+            && self.matches_key(&secret.key)
+            && self
+                .cutoff
+                .map_or(true, |cutoff| secret.revision_date < cutoff)
+    }
 }
 
 pub(crate) async fn process_command(
@@ -38,10 +134,19 @@ pub(crate) async fn process_command(
     client: Client,
     organization_id: Uuid,
     output_settings: OutputSettings,
+    confirmation: Confirmation,
+    journal: Option<Journal>,
 ) -> Result<()> {
     match command {
-        SecretCommand::List { project_id } => {
-            list(client, organization_id, project_id, output_settings).await
+        SecretCommand::List { project_id, tags } => {
+            list(client, organization_id, project_id, &tags, output_settings).await
+        }
+        SecretCommand::Tag { cmd } => {
+            confirmation.confirm_protected()?;
+            metadata::tag(client, organization_id, cmd, output_settings).await
+        }
+        SecretCommand::Expiring { within, project_id } => {
+            metadata::expiring(client, organization_id, within, project_id, output_settings).await
         }
         SecretCommand::Get { secret_id } => get(client, secret_id, output_settings).await,
         SecretCommand::Create {
@@ -64,12 +169,17 @@ pub(crate) async fn process_command(
             .await
         }
         SecretCommand::Edit {
-            secret_id,
+            secret_id: Some(secret_id),
             key,
             value,
             note,
             project_id,
+            dry_run,
+            ..
         } => {
+            if !dry_run {
+                confirmation.confirm_protected()?;
+            }
             edit(
                 client,
                 organization_id,
@@ -79,12 +189,95 @@ pub(crate) async fn process_command(
                     value,
                     note,
                     project_id,
+                    dry_run,
+                },
+                output_settings,
+            )
+            .await
+        }
+        SecretCommand::Edit {
+            secret_id: None,
+            value,
+            note,
+            project_id,
+            filter_project_id,
+            filter_key,
+            filter_older_than,
+            dry_run,
+            ..
+        } => {
+            edit_matching(
+                client,
+                organization_id,
+                SecretBulkEditCommandModel {
+                    filter: SecretFilter {
+                        project_id: filter_project_id,
+                        key: filter_key,
+                        older_than: filter_older_than,
+                    },
+                    value,
+                    note,
+                    project_id,
+                    dry_run,
                 },
                 output_settings,
+                confirmation,
+            )
+            .await
+        }
+        SecretCommand::Rename {
+            project_id,
+            pattern,
+            replace,
+            to_upper_snake,
+            to_lower_snake,
+            dry_run,
+        } => {
+            let case = match (to_upper_snake, to_lower_snake) {
+                (true, _) => Some(KeyCase::UpperSnake),
+                (_, true) => Some(KeyCase::LowerSnake),
+                _ => None,
+            };
+            rename::rename(
+                client,
+                organization_id,
+                SecretRenameCommandModel {
+                    project_id,
+                    pattern,
+                    replace,
+                    case,
+                    dry_run,
+                },
+                output_settings,
+                confirmation,
+            )
+            .await
+        }
+        SecretCommand::Delete {
+            secret_ids,
+            project_id,
+            key,
+            older_than,
+            dry_run,
+        } => {
+            delete(
+                client,
+                organization_id,
+                SecretDeleteCommandModel {
+                    secret_ids,
+                    filter: SecretFilter {
+                        project_id,
+                        key,
+                        older_than,
+                    },
+                    dry_run,
+                },
+                output_settings,
+                confirmation,
+                journal,
             )
             .await
         }
-        SecretCommand::Delete { secret_ids } => delete(client, secret_ids).await,
     }
 }
 
@@ -92,6 +285,7 @@ pub(crate) async fn list(
     client: Client,
     organization_id: Uuid,
     project_id: Option<Uuid>,
+    tags: &[String],
     output_settings: OutputSettings,
 ) -> Result<()> {
     let res = if let Some(project_id) = project_id {
@@ -111,7 +305,12 @@ pub(crate) async fn list(
         .secrets()
         .get_by_ids(SecretsGetRequest { ids: secret_ids })
         .await?
-        .data;
+        .data
+        .into_iter()
+        .filter(|s| has_tags(&s.note, tags))
+        .collect();
+    let secrets = mask_values(secrets, &output_settings);
+    completion::cache_secrets(secrets.iter().map(|s| (s.id, s.key.clone())));
     serialize_response(secrets, output_settings);
 
     Ok(())
@@ -122,10 +321,11 @@ pub(crate) async fn get(
     secret_id: Uuid,
     output_settings: OutputSettings,
 ) -> Result<()> {
-    let secret = client
+    let mut secret = client
         .secrets()
         .get(&SecretGetRequest { id: secret_id })
         .await?;
+    secret.value = output_settings.mask(secret.value);
     serialize_response(secret, output_settings);
 
     Ok(())
@@ -137,7 +337,7 @@ pub(crate) async fn create(
     secret: SecretCreateCommandModel,
     output_settings: OutputSettings,
 ) -> Result<()> {
-    let secret = client
+    let mut secret = client
         .secrets()
         .create(&SecretCreateRequest {
             organization_id,
@@ -147,6 +347,8 @@ pub(crate) async fn create(
             project_ids: Some(vec![secret.project_id]),
         })
         .await?;
+    audit::record(AuditAction::Create, [(secret.id, secret.key.clone())])?;
+    secret.value = output_settings.mask(secret.value);
     serialize_response(secret, output_settings);
 
     Ok(())
@@ -158,12 +360,21 @@ pub(crate) async fn edit(
     secret: SecretEditCommandModel,
     output_settings: OutputSettings,
 ) -> Result<()> {
-    let old_secret = client
+    let mut old_secret = client
         .secrets()
         .get(&SecretGetRequest { id: secret.id })
         .await?;
 
-    let new_secret = client
+    if secret.dry_run {
+        old_secret.key = secret.key.unwrap_or(old_secret.key);
+        old_secret.value = output_settings.mask(secret.value.unwrap_or(old_secret.value));
+        old_secret.note = secret.note.unwrap_or(old_secret.note);
+        old_secret.project_id = secret.project_id.or(old_secret.project_id);
+        serialize_response(old_secret, output_settings);
+        return Ok(());
+    }
+
+    let mut new_secret = client
         .secrets()
         .update(&SecretPutRequest {
             id: secret.id,
@@ -177,18 +388,170 @@ pub(crate) async fn edit(
                 .map(|id| vec![id]),
         })
         .await?;
+    audit::record(
+        AuditAction::Update,
+        [(new_secret.id, new_secret.key.clone())],
+    )?;
+    new_secret.value = output_settings.mask(new_secret.value);
     serialize_response(new_secret, output_settings);
 
     Ok(())
 }
 
-pub(crate) async fn delete(client: Client, secret_ids: Vec<Uuid>) -> Result<()> {
-    let count = secret_ids.len();
+/// Applies the same changes to every secret matching the filter.
+pub(crate) async fn edit_matching(
+    client: Client,
+    organization_id: Uuid,
+    edit: SecretBulkEditCommandModel,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+) -> Result<()> {
+    edit.filter.require_selector()?;
+    let secrets = find_secrets(&client, organization_id, &edit.filter).await?;
+
+    if edit.dry_run {
+        let edited = secrets
+            .into_iter()
+            .map(|mut s| {
+                s.value = edit.value.clone().unwrap_or(s.value);
+                s.note = edit.note.clone().unwrap_or(s.note);
+                s.project_id = edit.project_id.or(s.project_id);
+                s
+            })
+            .collect();
+        serialize_response(mask_values(edited, &output_settings), output_settings);
+        return Ok(());
+    }
+
+    if secrets.is_empty() {
+        eprintln!("No secrets matched.");
+        return Ok(());
+    }
+
+    let names: Vec<(Uuid, String)> = secrets.iter().map(|s| (s.id, s.key.clone())).collect();
+    confirmation.confirm(&format!("Edit {} secrets?", secrets.len()), &names)?;
+
+    let mut edited = Vec::new();
+    let mut secrets_failed = Vec::new();
+    for secret in secrets {
+        let result = client
+            .secrets()
+            .update(&SecretPutRequest {
+                id: secret.id,
+                organization_id,
+                key: secret.key,
+                value: edit.value.clone().unwrap_or(secret.value),
+                note: edit.note.clone().unwrap_or(secret.note),
+                project_ids: edit.project_id.or(secret.project_id).map(|id| vec![id]),
+            })
+            .await;
+
+        match result {
+            Ok(secret) => edited.push(secret),
+            Err(e) => secrets_failed.push((secret.id, e.to_string())),
+        }
+    }
 
-    let result = client
+    audit::record(
+        AuditAction::Update,
+        edited.iter().map(|s| (s.id, s.key.clone())),
+    )?;
+    serialize_response(mask_values(edited, &output_settings), output_settings);
+
+    report_partial_failure(
+        "secret",
+        "Errors when attempting to edit secrets.",
+        &secrets_failed,
+    )?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete(
+    client: Client,
+    organization_id: Uuid,
+    delete: SecretDeleteCommandModel,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+    journal: Option<Journal>,
+) -> Result<()> {
+    // Without a journal, the secrets are only looked up when prompting, so automation with --yes
+    // skips the lookup
+    if delete.filter.is_empty() && !delete.dry_run && journal.is_none() && confirmation.yes {
+        return delete_secrets(client, delete.secret_ids, Vec::new(), None).await;
+    }
+
+    if delete.secret_ids.is_empty() {
+        delete.filter.require_selector()?;
+    }
+    let secrets = match delete.filter.is_empty() {
+        true => get_secrets(&client, &delete.secret_ids).await,
+        false => find_secrets(&client, organization_id, &delete.filter).await?,
+    };
+
+    if delete.dry_run {
+        serialize_response(mask_values(secrets, &output_settings), output_settings);
+        return Ok(());
+    }
+
+    // Secrets that couldn't be found are still passed on, so their errors are reported
+    let mut secret_ids: Vec<Uuid> = secrets.iter().map(|s| s.id).collect();
+    let mut names: Vec<(Uuid, String)> = secrets.iter().map(|s| (s.id, s.key.clone())).collect();
+    for id in &delete.secret_ids {
+        if !secret_ids.contains(id) {
+            secret_ids.push(*id);
+            names.push((*id, "<not found>".to_string()));
+        }
+    }
+
+    if secret_ids.is_empty() {
+        eprintln!("No secrets matched.");
+        return Ok(());
+    }
+
+    confirmation.confirm(&format!("Delete {} secrets?", secret_ids.len()), &names)?;
+
+    let entry = match &journal {
+        Some(journal) => Some((
+            journal,
+            journal.record(
+                organization_id,
+                Vec::new(),
+                secrets.into_iter().map(Into::into).collect(),
+                Vec::new(),
+            )?,
+        )),
+        None => None,
+    };
+
+    delete_secrets(client, secret_ids, names, entry).await
+}
+
+/// Deletes the secrets, recording the deleted ones with their key from `names` when it's known.
+/// The journal entry, if any, is left with only the secrets that were deleted.
+async fn delete_secrets(
+    client: Client,
+    secret_ids: Vec<Uuid>,
+    names: Vec<(Uuid, String)>,
+    entry: Option<(&Journal, Uuid)>,
+) -> Result<()> {
+    let count = secret_ids.len();
+    let result = match client
         .secrets()
-        .delete(SecretsDeleteRequest { ids: secret_ids })
-        .await?;
+        .delete(SecretsDeleteRequest {
+            ids: secret_ids.clone(),
+        })
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            // Nothing was deleted, so there's nothing to undo
+            if let Some((journal, entry_id)) = entry {
+                journal.remove(entry_id)?;
+            }
+            return Err(e.into());
+        }
+    };
 
     let secrets_failed: Vec<(Uuid, String)> = result
         .data
@@ -196,6 +559,18 @@ pub(crate) async fn delete(client: Client, secret_ids: Vec<Uuid>) -> Result<()>
         .filter_map(|r| r.error.map(|e| (r.id, e)))
         .collect();
     let deleted_secrets = count - secrets_failed.len();
+    let deleted_ids: Vec<Uuid> = secret_ids
+        .into_iter()
+        .filter(|id| !secrets_failed.iter().any(|(failed, _)| failed == id))
+        .collect();
+
+    audit::record(
+        AuditAction::Delete,
+        deleted_ids.iter().map(|id| {
+            let key = names.iter().find(|(n, _)| n == id).map(|(_, key)| key);
+            (*id, key.cloned().unwrap_or_default())
+        }),
+    )?;
 
     match deleted_secrets {
         2.. => println!("{} secrets deleted successfully.", deleted_secrets),
@@ -203,19 +578,150 @@ pub(crate) async fn delete(client: Client, secret_ids: Vec<Uuid>) -> Result<()>
         _ => (),
     }
 
-    match secrets_failed.len() {
-        2.. => eprintln!("{} secrets had errors:", secrets_failed.len()),
-        1 => eprintln!("{} secret had an error:", secrets_failed.len()),
-        _ => (),
+    if let Some((journal, entry_id)) = entry {
+        if journal.reconcile(entry_id, &deleted_ids)? {
+            eprintln!("Run `bws undo --id {entry_id}` to recreate them.");
+        }
+    }
+
+    report_partial_failure(
+        "secret",
+        "Errors when attempting to delete secrets.",
+        &secrets_failed,
+    )?;
+
+    Ok(())
+}
+
+/// Looks up the given secrets, skipping the ones that can't be found.
+async fn get_secrets(client: &Client, secret_ids: &[Uuid]) -> Vec<SecretResponse> {
+    if let Ok(secrets) = client
+        .secrets()
+        .get_by_ids(SecretsGetRequest {
+            ids: secret_ids.to_vec(),
+        })
+        .await
+    {
+        return secrets.data;
     }
 
-    for secret in &secrets_failed {
-        eprintln!("{}: {}", secret.0, secret.1);
+    // A single missing secret fails the whole request, so fall back to getting them one by one
+    let mut secrets = Vec::new();
+    for id in secret_ids {
+        if let Ok(secret) = client.secrets().get(&SecretGetRequest { id: *id }).await {
+            secrets.push(secret);
+        }
     }
 
-    if !secrets_failed.is_empty() {
-        bail!("Errors when attempting to delete secrets.");
+    secrets
+}
+
+/// Returns the secrets matching every selector of the filter.
+pub(crate) async fn find_secrets(
+    client: &Client,
+    organization_id: Uuid,
+    filter: &SecretFilter,
+) -> Result<Vec<SecretResponse>> {
+    let matcher = filter.matcher(Utc::now())?;
+
+    let identifiers = if let Some(project_id) = filter.project_id {
+        client
+            .secrets()
+            .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
+            .await?
+    } else {
+        client
+            .secrets()
+            .list(&SecretIdentifiersRequest { organization_id })
+            .await?
+    };
+
+    let secret_ids: Vec<Uuid> = identifiers
+        .data
+        .into_iter()
+        .filter(|s| matcher.matches_key(&s.key))
+        .map(|s| s.id)
+        .collect();
+    if secret_ids.is_empty() {
+        return Ok(Vec::new());
     }
 
-    Ok(())
+    Ok(client
+        .secrets()
+        .get_by_ids(SecretsGetRequest { ids: secret_ids })
+        .await?
+        .data
+        .into_iter()
+        .filter(|s| matcher.matches(s))
+        .collect())
+}
+
+fn mask_values(
+    secrets: Vec<SecretResponse>,
+    output_settings: &OutputSettings,
+) -> Vec<SecretResponse> {
+    secrets
+        .into_iter()
+        .map(|mut s| {
+            s.value = output_settings.mask(s.value);
+            s
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::test_util::secret;
+
+    #[test]
+    fn older_than_excludes_the_cutoff() {
+        let now = Utc::now();
+        let matcher = SecretFilter {
+            older_than: Some("30d".to_string()),
+            ..Default::default()
+        }
+        .matcher(now)
+        .unwrap();
+        let cutoff = now - TimeDelta::days(30);
+
+        let revised = |revision_date| SecretResponse {
+            revision_date,
+            ..secret("KEY", "value", None)
+        };
+        assert!(!matcher.matches(&revised(cutoff)));
+        assert!(matcher.matches(&revised(cutoff - TimeDelta::seconds(1))));
+    }
+
+    #[test]
+    fn key_glob_and_project_must_both_match() {
+        let project = Uuid::new_v4();
+        let now = Utc::now();
+        let matcher = SecretFilter {
+            project_id: Some(project),
+            key: Some("DB_*".to_string()),
+            ..Default::default()
+        }
+        .matcher(now)
+        .unwrap();
+
+        assert!(matcher.matches(&secret("DB_PASSWORD", "value", Some(project))));
+        assert!(!matcher.matches(&secret("API_KEY", "value", Some(project))));
+        assert!(!matcher.matches(&secret("DB_PASSWORD", "value", Some(Uuid::new_v4()))));
+        assert!(!matcher.matches(&secret("DB_PASSWORD", "value", None)));
+        assert!(!matcher.matches_key("MY_DB_PASSWORD"));
+    }
+
+    #[test]
+    fn empty_filter_is_refused() {
+        assert!(SecretFilter::default().require_selector().is_err());
+        assert!(SecretFilter {
+            key: Some("*".to_string()),
+            ..Default::default()
+        }
+        .require_selector()
+        .is_ok());
+    }
 }
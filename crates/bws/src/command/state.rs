@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+
+use crate::{
+    render::{serialize_response, OutputSettings},
+    state::{get_state_dir, list_state_files},
+    util::parse_duration,
+    StateCommand,
+};
+
+pub(crate) fn process_command(
+    command: StateCommand,
+    state_dir: Option<PathBuf>,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let state_dir = get_state_dir(state_dir)?;
+
+    match command {
+        StateCommand::List => {
+            serialize_response(list_state_files(&state_dir)?, output_settings);
+            Ok(())
+        }
+        StateCommand::Clear => remove_state_files(state_dir, None),
+        StateCommand::Prune { older_than } => {
+            remove_state_files(state_dir, Some(parse_duration(&older_than)?))
+        }
+    }
+}
+
+fn remove_state_files(state_dir: PathBuf, older_than: Option<chrono::TimeDelta>) -> Result<()> {
+    let now = Utc::now();
+
+    let mut removed = 0;
+    for state_file in list_state_files(&state_dir)? {
+        if let Some(older_than) = older_than {
+            if now - state_file.revision_date < older_than {
+                continue;
+            }
+        }
+
+        std::fs::remove_file(&state_file.path)?;
+        removed += 1;
+    }
+
+    match removed {
+        2.. => println!("{} state files deleted successfully.", removed),
+        1 => println!("{} state file deleted successfully.", removed),
+        _ => println!("No state files to delete."),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::{path::PathBuf, time::Instant};
+
+use bitwarden::{auth::login::AccessTokenLoginRequest, Client, ClientSettings};
+use chrono::{DateTime, TimeDelta, Utc};
+use color_eyre::eyre::{bail, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    config::ConfigSource,
+    error::{CliError, ErrorCategory},
+    render::{serialize_response, OutputSettings},
+    util::with_timeout,
+};
+
+#[derive(Debug)]
+pub(crate) struct StatusCommandModel {
+    pub(crate) access_token: String,
+    pub(crate) access_token_id: Uuid,
+    pub(crate) api_url: String,
+    pub(crate) identity_url: String,
+    pub(crate) profile: String,
+    pub(crate) profile_source: ConfigSource,
+    pub(crate) state_file: Option<PathBuf>,
+    pub(crate) check: bool,
+    pub(crate) timeout: Option<TimeDelta>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StatusResponse {
+    pub(crate) access_token_id: Uuid,
+    pub(crate) organization_id: Option<Uuid>,
+    pub(crate) api_url: String,
+    pub(crate) identity_url: String,
+    pub(crate) profile: String,
+    pub(crate) profile_source: ConfigSource,
+    pub(crate) state_file: Option<PathBuf>,
+    pub(crate) state_file_revision_date: Option<DateTime<Utc>>,
+    pub(crate) check: Option<StatusCheck>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StatusCheck {
+    pub(crate) success: bool,
+    pub(crate) latency_ms: u128,
+    pub(crate) error_category: Option<ErrorCategory>,
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) async fn status(
+    client: Client,
+    status: StatusCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    // An empty state file hasn't been written by a login yet
+    let state_file_revision_date = status
+        .state_file
+        .as_ref()
+        .and_then(|f| std::fs::metadata(f).ok())
+        .filter(|m| m.len() > 0)
+        .and_then(|m| m.modified().ok())
+        .map(DateTime::<Utc>::from);
+
+    // Without --check, the organization comes from the saved session when possible. Without a
+    // valid session this logs in, so it's bound by the timeout like any other request.
+    let mut organization_id = None;
+    if !status.check {
+        let login = with_timeout(
+            status.timeout,
+            client.auth().login_access_token(&AccessTokenLoginRequest {
+                access_token: status.access_token.clone(),
+                state_file: status.state_file.clone(),
+            }),
+        )
+        .await
+        .and_then(|result| Ok(result?));
+        match login {
+            Ok(_) => organization_id = client.internal.get_access_token_organization(),
+            Err(e) => eprintln!("Warning: Couldn't determine the organization: {}", e),
+        }
+    }
+
+    let check = if status.check {
+        let client = Client::new(Some(ClientSettings {
+            api_url: status.api_url.clone(),
+            identity_url: status.identity_url.clone(),
+            ..Default::default()
+        }));
+
+        let start = Instant::now();
+        let result = with_timeout(
+            status.timeout,
+            client.auth().login_access_token(&AccessTokenLoginRequest {
+                access_token: status.access_token,
+                state_file: None,
+            }),
+        )
+        .await
+        .and_then(|result| Ok(result?));
+        let latency_ms = start.elapsed().as_millis();
+
+        Some(match result {
+            Ok(_) => {
+                organization_id = client.internal.get_access_token_organization();
+                StatusCheck {
+                    success: true,
+                    latency_ms,
+                    error_category: None,
+                    error: None,
+                }
+            }
+            Err(e) => StatusCheck {
+                success: false,
+                latency_ms,
+                error_category: Some(ErrorCategory::from_report(&e)),
+                error: Some(e.to_string()),
+            },
+        })
+    } else {
+        None
+    };
+
+    let failure = check.as_ref().and_then(|c| c.error_category);
+
+    serialize_response(
+        StatusResponse {
+            access_token_id: status.access_token_id,
+            organization_id,
+            api_url: status.api_url,
+            identity_url: status.identity_url,
+            profile: status.profile,
+            profile_source: status.profile_source,
+            state_file: status.state_file,
+            state_file_revision_date,
+            check,
+        },
+        output_settings,
+    );
+
+    if let Some(category) = failure {
+        bail!(CliError::new(category, "Login check failed."));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use bitwarden::{
+    secrets_manager::{
+        projects::{ProjectResponse, ProjectsListRequest},
+        secrets::{
+            SecretCreateRequest, SecretIdentifiersRequest, SecretPutRequest, SecretResponse,
+            SecretsDeleteRequest, SecretsGetRequest,
+        },
+        ClientProjectsExt, ClientSecretsExt,
+    },
+    Client,
+};
+use color_eyre::eyre::{bail, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyEventKind},
+    DefaultTerminal,
+};
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    backup::BackupSecret,
+    confirm::Confirmation,
+    journal::Journal,
+    render::OutputSettings,
+    tui::{ui, Action, App, Mode, SecretForm, Status},
+};
+
+/// Browses and modifies projects and secrets in a terminal UI. API errors are shown in the status
+/// line, so a failed request doesn't end the session.
+pub(crate) async fn tui(
+    client: Client,
+    organization_id: Uuid,
+    output_settings: OutputSettings,
+    confirmation: Confirmation,
+    journal: Option<Journal>,
+) -> Result<()> {
+    let mut app = App::new(
+        output_settings.reveal,
+        confirmation.protected_profile.is_some(),
+    );
+
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(
+        &mut terminal,
+        &client,
+        organization_id,
+        journal.as_ref(),
+        &mut app,
+    )
+    .await;
+    ratatui::restore();
+
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: &Client,
+    organization_id: Uuid,
+    journal: Option<&Journal>,
+    app: &mut App,
+) -> Result<()> {
+    let mut action = Action::Reload;
+
+    loop {
+        match action {
+            Action::None => (),
+            Action::Quit => return Ok(()),
+            Action::Reload => {
+                show_progress(terminal, app, "Loading projects and secrets...")?;
+                match load(client, organization_id).await {
+                    Ok((projects, secrets)) => {
+                        app.set_data(projects, secrets);
+                        app.status = app.read_only.then(|| {
+                            Status::Info(
+                                "The profile is protected, so secrets can only be browsed here"
+                                    .to_string(),
+                            )
+                        });
+                    }
+                    Err(e) => app.status = Some(Status::Error(e.to_string())),
+                }
+            }
+            Action::Save(form) => {
+                show_progress(terminal, app, &format!("Saving {}...", form.key))?;
+                match save(client, organization_id, &form).await {
+                    Ok(secret) => {
+                        app.status = Some(Status::Info(format!("Saved {}", secret.key)));
+                        app.upsert_secret(secret);
+                    }
+                    Err(e) => {
+                        // Keep the form open, so the changes aren't lost
+                        app.status = Some(Status::Error(e.to_string()));
+                        app.mode = Mode::Form(form);
+                    }
+                }
+            }
+            Action::Delete(id) => {
+                if let Some(index) = app.secrets.iter().position(|s| s.id == id) {
+                    let key = app.secrets[index].key.clone();
+                    show_progress(terminal, app, &format!("Deleting {key}..."))?;
+                    let result =
+                        delete(client, organization_id, journal, &app.secrets[index]).await;
+                    if result.is_ok() {
+                        app.remove_secret(id);
+                    }
+                    app.status = Some(match result {
+                        Ok(Some(entry_id)) => Status::Info(format!(
+                            "Deleted {key}. Run `bws undo --id {entry_id}` to recreate it."
+                        )),
+                        Ok(None) => Status::Info(format!("Deleted {key}")),
+                        Err(e) => Status::Error(e.to_string()),
+                    });
+                }
+            }
+        }
+
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        action = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+            _ => Action::None,
+        };
+    }
+}
+
+fn show_progress(terminal: &mut DefaultTerminal, app: &mut App, message: &str) -> Result<()> {
+    app.status = Some(Status::Info(message.to_string()));
+    terminal.draw(|frame| ui::draw(frame, app))?;
+    Ok(())
+}
+
+async fn load(
+    client: &Client,
+    organization_id: Uuid,
+) -> Result<(Vec<ProjectResponse>, Vec<SecretResponse>)> {
+    let projects = client
+        .projects()
+        .list(&ProjectsListRequest { organization_id })
+        .await?
+        .data;
+
+    let ids: Vec<Uuid> = client
+        .secrets()
+        .list(&SecretIdentifiersRequest { organization_id })
+        .await?
+        .data
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    let secrets = match ids.is_empty() {
+        true => Vec::new(),
+        false => {
+            client
+                .secrets()
+                .get_by_ids(SecretsGetRequest { ids })
+                .await?
+                .data
+        }
+    };
+
+    Ok((projects, secrets))
+}
+
+async fn save(client: &Client, organization_id: Uuid, form: &SecretForm) -> Result<SecretResponse> {
+    let project_ids = form.project_id.map(|id| vec![id]);
+
+    let secret = match form.id {
+        Some(id) => {
+            let secret = client
+                .secrets()
+                .update(&SecretPutRequest {
+                    id,
+                    organization_id,
+                    key: form.key.clone(),
+                    value: form.value.clone(),
+                    note: form.note.clone(),
+                    project_ids,
+                })
+                .await?;
+            audit::record(AuditAction::Update, [(secret.id, secret.key.clone())])?;
+            secret
+        }
+        None => {
+            let secret = client
+                .secrets()
+                .create(&SecretCreateRequest {
+                    organization_id,
+                    key: form.key.clone(),
+                    value: form.value.clone(),
+                    note: form.note.clone(),
+                    project_ids,
+                })
+                .await?;
+            audit::record(AuditAction::Create, [(secret.id, secret.key.clone())])?;
+            secret
+        }
+    };
+
+    Ok(secret)
+}
+
+/// Deletes a secret, recording it in the journal first. Returns the journal entry, if any.
+async fn delete(
+    client: &Client,
+    organization_id: Uuid,
+    journal: Option<&Journal>,
+    secret: &SecretResponse,
+) -> Result<Option<Uuid>> {
+    let entry_id = match journal {
+        Some(journal) => Some(journal.record(
+            organization_id,
+            Vec::new(),
+            vec![BackupSecret {
+                id: secret.id,
+                project_id: secret.project_id,
+                key: secret.key.clone(),
+                value: secret.value.clone(),
+                note: secret.note.clone(),
+            }],
+            Vec::new(),
+        )?),
+        None => None,
+    };
+
+    let result = client
+        .secrets()
+        .delete(SecretsDeleteRequest {
+            ids: vec![secret.id],
+        })
+        .await;
+    let error = match result {
+        Ok(response) => response.data.into_iter().find_map(|r| r.error),
+        Err(e) => Some(e.to_string()),
+    };
+
+    if let Some(error) = error {
+        // Nothing was deleted, so there's nothing to undo
+        if let (Some(journal), Some(entry_id)) = (journal, entry_id) {
+            journal.remove(entry_id)?;
+        }
+        bail!(error);
+    }
+
+    audit::record(AuditAction::Delete, [(secret.id, secret.key.clone())])?;
+    Ok(entry_id)
+}
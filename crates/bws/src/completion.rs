@@ -0,0 +1,359 @@
+use std::{collections::BTreeMap, ffi::OsString, path::PathBuf, sync::OnceLock};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::{Arg, Command, CommandFactory};
+use clap_complete::{
+    engine::{ArgValueCandidates, CompletionCandidate},
+    Shell,
+};
+use color_eyre::eyre::{bail, Result};
+use directories::BaseDirs;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    util::{create_private_dir, write_private_file},
+    Cli, DEFAULT_CONFIG_DIRECTORY,
+};
+
+pub(crate) const DEFAULT_CACHE_DIRECTORY: &str = "cache";
+const COMPLETION_CACHE_FILENAME: &str = "completions.json";
+const COMPLETION_CACHE_TTL_MINUTES: i64 = 60;
+
+/// The profile and organization of this run, which the listed identifiers are cached for.
+static CACHE_SCOPE: OnceLock<CacheScope> = OnceLock::new();
+
+/// The profile being completed for, from the words on the command line.
+static COMPLETION_PROFILE: OnceLock<String> = OnceLock::new();
+
+struct CacheScope {
+    profile: String,
+    organization_id: Uuid,
+}
+
+/// Project and secret identifiers from the last `list` calls of each profile, used for shell
+/// completion so that completing never has to hit the network. Secret values are never stored
+/// here.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct CompletionCache {
+    profiles: BTreeMap<String, ProfileCache>,
+}
+
+/// The identifiers of the organization a profile last listed. Listing another organization with
+/// the same profile replaces them, so completion never offers identifiers of two organizations.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileCache {
+    organization_id: Uuid,
+    projects: Option<CachedEntries>,
+    secrets: Option<CachedEntries>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedEntries {
+    updated: DateTime<Utc>,
+    entries: Vec<CachedEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEntry {
+    id: Uuid,
+    name: String,
+}
+
+impl CachedEntries {
+    fn new(entries: impl IntoIterator<Item = (Uuid, String)>) -> Self {
+        CachedEntries {
+            updated: Utc::now(),
+            entries: entries
+                .into_iter()
+                .map(|(id, name)| CachedEntry { id, name })
+                .collect(),
+        }
+    }
+
+    fn candidates(entries: &Option<CachedEntries>) -> Vec<CompletionCandidate> {
+        let ttl = TimeDelta::minutes(COMPLETION_CACHE_TTL_MINUTES);
+
+        match entries {
+            Some(entries) if Utc::now() - entries.updated < ttl => entries
+                .entries
+                .iter()
+                .map(|e| {
+                    CompletionCandidate::new(e.id.to_string()).help(Some(e.name.clone().into()))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn get_cache_file() -> Result<PathBuf> {
+    let Some(base_dirs) = BaseDirs::new() else {
+        bail!("A valid home directory doesn't exist");
+    };
+
+    Ok(base_dirs
+        .home_dir()
+        .join(DEFAULT_CONFIG_DIRECTORY)
+        .join(DEFAULT_CACHE_DIRECTORY)
+        .join(COMPLETION_CACHE_FILENAME))
+}
+
+fn load_cache() -> CompletionCache {
+    get_cache_file()
+        .ok()
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Sets the profile and organization that listed identifiers are cached for. Nothing is cached
+/// until this is called.
+pub(crate) fn init(profile: String, organization_id: Uuid) {
+    let _ = CACHE_SCOPE.set(CacheScope {
+        profile,
+        organization_id,
+    });
+}
+
+fn update_cache(update: impl FnOnce(&mut ProfileCache)) -> Result<()> {
+    let Some(scope) = CACHE_SCOPE.get() else {
+        return Ok(());
+    };
+
+    let file = get_cache_file()?;
+    if let Some(parent) = file.parent() {
+        create_private_dir(parent)?;
+    }
+
+    let mut cache = load_cache();
+    let profile_cache = cache
+        .profiles
+        .entry(scope.profile.clone())
+        .or_insert_with(|| ProfileCache::new(scope.organization_id));
+    if profile_cache.organization_id != scope.organization_id {
+        *profile_cache = ProfileCache::new(scope.organization_id);
+    }
+    update(profile_cache);
+
+    write_private_file(&file, serde_json::to_string(&cache)?)
+}
+
+impl ProfileCache {
+    fn new(organization_id: Uuid) -> Self {
+        ProfileCache {
+            organization_id,
+            projects: None,
+            secrets: None,
+        }
+    }
+}
+
+/// Remembers the listed projects for completion. Failures are ignored since the cache is only a
+/// convenience.
+pub(crate) fn cache_projects(projects: impl IntoIterator<Item = (Uuid, String)>) {
+    let entries = CachedEntries::new(projects);
+    let _ = update_cache(|cache| cache.projects = Some(entries));
+}
+
+/// Remembers the listed secret keys for completion. Failures are ignored since the cache is only a
+/// convenience.
+pub(crate) fn cache_secrets(secrets: impl IntoIterator<Item = (Uuid, String)>) {
+    let entries = CachedEntries::new(secrets);
+    let _ = update_cache(|cache| cache.secrets = Some(entries));
+}
+
+fn profile_candidates(
+    entries: impl FnOnce(&ProfileCache) -> &Option<CachedEntries>,
+) -> Vec<CompletionCandidate> {
+    let Some(profile) = COMPLETION_PROFILE.get() else {
+        return Vec::new();
+    };
+    match load_cache().profiles.get(profile) {
+        Some(cache) => CachedEntries::candidates(entries(cache)),
+        None => Vec::new(),
+    }
+}
+
+fn project_candidates() -> Vec<CompletionCandidate> {
+    profile_candidates(|cache| &cache.projects)
+}
+
+fn secret_candidates() -> Vec<CompletionCandidate> {
+    profile_candidates(|cache| &cache.secrets)
+}
+
+fn projects(arg: Arg) -> Arg {
+    arg.add(ArgValueCandidates::new(project_candidates))
+}
+
+fn secrets(arg: Arg) -> Arg {
+    arg.add(ArgValueCandidates::new(secret_candidates))
+}
+
+/// The CLI definition with dynamic completers attached to the project and secret ID arguments.
+pub(crate) fn command_with_completers() -> Command {
+    Cli::command()
+        .mut_subcommand("project", |c| {
+            c.mut_subcommand("get", |c| c.mut_arg("project_id", projects))
+                .mut_subcommand("edit", |c| c.mut_arg("project_id", projects))
+                .mut_subcommand("delete", |c| c.mut_arg("project_ids", projects))
+                .mut_subcommand("copy", |c| {
+                    c.mut_arg("from", projects).mut_arg("to", projects)
+                })
+        })
+        .mut_subcommand("secret", |c| {
+            c.mut_subcommand("get", |c| c.mut_arg("secret_id", secrets))
+                .mut_subcommand("edit", |c| {
+                    c.mut_arg("secret_id", secrets)
+                        .mut_arg("project_id", projects)
+                        .mut_arg("filter_project_id", projects)
+                })
+                .mut_subcommand("delete", |c| {
+                    c.mut_arg("secret_ids", secrets)
+                        .mut_arg("project_id", projects)
+                })
+                .mut_subcommand("create", |c| c.mut_arg("project_id", projects))
+                .mut_subcommand("list", |c| c.mut_arg("project_id", projects))
+                .mut_subcommand("rename", |c| c.mut_arg("project_id", projects))
+                .mut_subcommand("expiring", |c| c.mut_arg("project_id", projects))
+                .mut_subcommand("tag", |c| {
+                    c.mut_subcommand("add", |c| c.mut_arg("secret_id", secrets))
+                        .mut_subcommand("remove", |c| c.mut_arg("secret_id", secrets))
+                })
+        })
+        .mut_subcommand("run", |c| c.mut_arg("project_id", projects))
+        .mut_subcommand("check", |c| c.mut_arg("project_id", projects))
+        .mut_subcommand("ci", |c| {
+            c.mut_subcommand("export", |c| c.mut_arg("project_id", projects))
+        })
+        .mut_subcommand("diff", |c| c.mut_arg("project_id", projects))
+        .mut_subcommand("scan", |c| c.mut_arg("project_id", projects))
+        .mut_subcommand("report", |c| c.mut_arg("project_id", projects))
+}
+
+/// Prints the candidates for the word at `index` in `words`, in the format the registration
+/// script for `shell` expects. Identifiers are offered from the cache of the profile given in
+/// `words`, or else `default_profile`.
+pub(crate) fn complete(
+    shell: Shell,
+    index: usize,
+    words: Vec<OsString>,
+    default_profile: Option<String>,
+) -> Result<()> {
+    let profile = profile_from_words(&words)
+        .or(default_profile)
+        .unwrap_or_else(|| "default".to_string());
+    let _ = COMPLETION_PROFILE.set(profile);
+
+    let mut cmd = command_with_completers();
+    let current_dir = std::env::current_dir().ok();
+    let candidates =
+        clap_complete::engine::complete(&mut cmd, words, index, current_dir.as_deref())?;
+
+    for candidate in candidates.iter().filter(|c| !c.is_hide_set()) {
+        let value = candidate.get_value().to_string_lossy();
+        let help = candidate
+            .get_help()
+            .map(|h| h.to_string().lines().next().unwrap_or_default().to_string());
+
+        match (shell, help) {
+            (Shell::Zsh, Some(help)) => println!("{}:{}", value.replace(':', "\\:"), help),
+            (Shell::Zsh, None) => println!("{}", value.replace(':', "\\:")),
+            (Shell::Fish, Some(help)) => println!("{}\t{}", value, help),
+            _ => println!("{}", value),
+        }
+    }
+
+    Ok(())
+}
+
+/// The value of `--profile` or `-p` among the words being completed.
+fn profile_from_words(words: &[OsString]) -> Option<String> {
+    let words: Vec<_> = words.iter().map(|w| w.to_string_lossy()).collect();
+
+    words.iter().enumerate().find_map(|(index, word)| {
+        if let Some(profile) = word.strip_prefix("--profile=") {
+            return Some(profile.to_string());
+        }
+        match word.as_ref() {
+            "--profile" | "-p" => words.get(index + 1).map(|p| p.to_string()),
+            _ => word
+                .strip_prefix("-p")
+                .filter(|p| !p.is_empty() && !word.starts_with("--"))
+                .map(|p| p.to_string()),
+        }
+    })
+}
+
+/// Returns the script that registers dynamic completion through `bws __complete` for `shell`.
+pub(crate) fn registration_script(shell: Shell, name: &str) -> Result<String> {
+    let script = match shell {
+        Shell::Bash => format!(
+            r#"_{name}_dynamic() {{
+    local IFS=$'\n'
+    COMPREPLY=( $("{name}" __complete bash "${{COMP_CWORD}}" -- "${{COMP_WORDS[@]}}" 2>/dev/null) )
+}}
+complete -o default -F _{name}_dynamic {name}
+"#
+        ),
+        Shell::Zsh => format!(
+            r#"#compdef {name}
+_{name}_dynamic() {{
+    local -a candidates
+    candidates=("${{(@f)$("{name}" __complete zsh "$((CURRENT - 1))" -- "${{words[@]}}" 2>/dev/null)}}")
+    _describe 'values' candidates
+}}
+compdef _{name}_dynamic {name}
+"#
+        ),
+        Shell::Fish => format!(
+            r#"complete -c {name} -f -a '("{name}" __complete fish (count (commandline -opc)) -- (commandline -opc) (commandline -ct) 2>/dev/null)'
+"#
+        ),
+        _ => bail!("Dynamic completions are only available for bash, zsh and fish"),
+    };
+
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completers_attach_to_existing_args() {
+        command_with_completers().debug_assert();
+    }
+
+    #[test]
+    fn profile_from_words_reads_flags() {
+        let words = |words: &[&str]| words.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            Some("staging".to_string()),
+            profile_from_words(&words(&["bws", "--profile", "staging", "secret", "get"]))
+        );
+        assert_eq!(
+            Some("staging".to_string()),
+            profile_from_words(&words(&["bws", "secret", "get", "--profile=staging"]))
+        );
+        assert_eq!(
+            Some("staging".to_string()),
+            profile_from_words(&words(&["bws", "-pstaging", "secret", "get"]))
+        );
+        assert_eq!(None, profile_from_words(&words(&["bws", "secret", "get"])));
+    }
+
+    #[test]
+    fn expired_cache_has_no_candidates() {
+        let mut entries = CachedEntries::new([(Uuid::new_v4(), "name".to_string())]);
+        assert_eq!(1, CachedEntries::candidates(&Some(entries)).len());
+
+        entries = CachedEntries::new([(Uuid::new_v4(), "name".to_string())]);
+        entries.updated -= TimeDelta::minutes(COMPLETION_CACHE_TTL_MINUTES + 1);
+        assert!(CachedEntries::candidates(&Some(entries)).is_empty());
+    }
+}
@@ -1,18 +1,33 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::read_to_string,
     path::{Path, PathBuf},
+    process::{Command, Stdio},
 };
 
+use bitwarden_cli::Color;
+use chrono::TimeDelta;
+use clap::ValueEnum;
 use color_eyre::eyre::{bail, Result};
 use directories::BaseDirs;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::cli::{ProfileKey, DEFAULT_CONFIG_DIRECTORY, DEFAULT_CONFIG_FILENAME};
+use crate::{
+    alias::Alias,
+    cli::{Output, ProfileKey, DEFAULT_CONFIG_DIRECTORY, DEFAULT_CONFIG_FILENAME},
+    error::{CliError, ErrorCategory},
+    util,
+};
+
+const DEFAULT_JOURNAL_RETENTION_DAYS: i64 = 30;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub(crate) struct Config {
+    #[serde(default)]
     pub profiles: HashMap<String, Profile>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, Alias>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
@@ -21,10 +36,126 @@ pub(crate) struct Profile {
     pub server_api: Option<String>,
     pub server_identity: Option<String>,
     pub state_dir: Option<String>,
+    #[serde(default, deserialize_with = "string_or_bool")]
     pub state_opt_out: Option<String>,
+    pub access_token_file: Option<String>,
+    pub access_token_command: Option<String>,
+    pub output: Option<String>,
+    pub color: Option<String>,
+    pub default_project: Option<String>,
+    #[serde(default, deserialize_with = "string_or_bool")]
+    pub reveal: Option<String>,
+    pub ca_cert_path: Option<String>,
+    pub http_proxy: Option<String>,
+    pub timeout: Option<String>,
+    #[serde(default, deserialize_with = "string_or_bool")]
+    pub protected: Option<String>,
+    pub journal_retention: Option<String>,
+    pub audit_log: Option<String>,
+}
+
+/// Where an effective setting was taken from, as shown by `bws config show`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ConfigSource {
+    Flag,
+    Env,
+    File,
+    LocalFile,
+    AccessToken,
+    Default,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self {
+            ConfigSource::Flag => "flag",
+            ConfigSource::Env => "env",
+            ConfigSource::File => "config file",
+            ConfigSource::LocalFile => ".bws.toml",
+            ConfigSource::AccessToken => "access token ID",
+            ConfigSource::Default => "default",
+        };
+        write!(f, "{source}")
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProfileSummary {
+    pub name: String,
+    pub api_url: Option<String>,
+    pub identity_url: Option<String>,
 }
 
 impl ProfileKey {
+    /// The name of the key in the config file.
+    pub(crate) fn name(&self) -> String {
+        format!("{self:?}")
+    }
+
+    pub(crate) fn profile_value<'a>(&self, p: &'a Profile) -> Option<&'a String> {
+        match self {
+            ProfileKey::server_base => p.server_base.as_ref(),
+            ProfileKey::server_api => p.server_api.as_ref(),
+            ProfileKey::server_identity => p.server_identity.as_ref(),
+            ProfileKey::state_dir => p.state_dir.as_ref(),
+            ProfileKey::state_opt_out => p.state_opt_out.as_ref(),
+            ProfileKey::access_token_file => p.access_token_file.as_ref(),
+            ProfileKey::access_token_command => p.access_token_command.as_ref(),
+            ProfileKey::output => p.output.as_ref(),
+            ProfileKey::color => p.color.as_ref(),
+            ProfileKey::default_project => p.default_project.as_ref(),
+            ProfileKey::reveal => p.reveal.as_ref(),
+            ProfileKey::ca_cert_path => p.ca_cert_path.as_ref(),
+            ProfileKey::http_proxy => p.http_proxy.as_ref(),
+            ProfileKey::timeout => p.timeout.as_ref(),
+            ProfileKey::protected => p.protected.as_ref(),
+            ProfileKey::journal_retention => p.journal_retention.as_ref(),
+            ProfileKey::audit_log => p.audit_log.as_ref(),
+        }
+    }
+
+    /// Checks that `value` has the right format for this key.
+    pub(crate) fn validate_value(&self, value: &str) -> Result<()> {
+        match self {
+            ProfileKey::server_base | ProfileKey::server_api | ProfileKey::server_identity => {
+                validate_url(value)
+            }
+            ProfileKey::state_opt_out | ProfileKey::reveal | ProfileKey::protected => {
+                match util::string_to_bool(value) {
+                    Ok(_) => Ok(()),
+                    Err(_) => bail!("must be \"true\" or \"false\""),
+                }
+            }
+            ProfileKey::output => validate_value_enum::<Output>(value),
+            ProfileKey::color => validate_value_enum::<Color>(value),
+            ProfileKey::default_project => match Uuid::parse_str(value) {
+                Ok(_) => Ok(()),
+                Err(_) => bail!("must be a valid project ID"),
+            },
+            ProfileKey::timeout | ProfileKey::journal_retention => {
+                util::parse_duration(value).map(|_| ())
+            }
+            ProfileKey::ca_cert_path if cfg!(any(target_os = "macos", target_os = "windows")) => {
+                bail!("isn't supported on this platform, the system trust store is always used")
+            }
+            ProfileKey::state_dir
+            | ProfileKey::access_token_file
+            | ProfileKey::access_token_command
+            | ProfileKey::ca_cert_path
+            | ProfileKey::http_proxy
+            | ProfileKey::audit_log => Ok(()),
+        }
+    }
+
     fn update_profile_value(&self, p: &mut Profile, value: String) {
         match self {
             ProfileKey::server_base => p.server_base = Some(value),
@@ -32,11 +163,26 @@ impl ProfileKey {
             ProfileKey::server_identity => p.server_identity = Some(value),
             ProfileKey::state_dir => p.state_dir = Some(value),
             ProfileKey::state_opt_out => p.state_opt_out = Some(value),
+            ProfileKey::access_token_file => p.access_token_file = Some(value),
+            ProfileKey::access_token_command => p.access_token_command = Some(value),
+            ProfileKey::output => p.output = Some(value),
+            ProfileKey::color => p.color = Some(value),
+            ProfileKey::default_project => p.default_project = Some(value),
+            ProfileKey::reveal => p.reveal = Some(value),
+            ProfileKey::ca_cert_path => p.ca_cert_path = Some(value),
+            ProfileKey::http_proxy => p.http_proxy = Some(value),
+            ProfileKey::timeout => p.timeout = Some(value),
+            ProfileKey::protected => p.protected = Some(value),
+            ProfileKey::journal_retention => p.journal_retention = Some(value),
+            ProfileKey::audit_log => p.audit_log = Some(value),
         }
     }
 }
 
-fn get_config_path(config_file: Option<&Path>, ensure_folder_exists: bool) -> Result<PathBuf> {
+pub(crate) fn get_config_path(
+    config_file: Option<&Path>,
+    ensure_folder_exists: bool,
+) -> Result<PathBuf> {
     let config_file = match config_file {
         Some(path) => path.to_owned(),
         None => {
@@ -111,9 +257,7 @@ pub(crate) fn delete_profile(config_file: Option<&Path>, profile: String) -> Res
 
 impl Profile {
     pub(crate) fn from_url(url: &str) -> Result<Profile> {
-        if !url.starts_with("http://") && !url.starts_with("https://") {
-            bail!("Server URL must start with http:// or https://, the provided URL is: `{url}`");
-        }
+        validate_url(url)?;
 
         Ok(Profile {
             server_base: Some(url.to_string()),
@@ -121,6 +265,18 @@ impl Profile {
             server_identity: None,
             state_dir: None,
             state_opt_out: None,
+            access_token_file: None,
+            access_token_command: None,
+            output: None,
+            color: None,
+            default_project: None,
+            reveal: None,
+            ca_cert_path: None,
+            http_proxy: None,
+            timeout: None,
+            protected: None,
+            journal_retention: None,
+            audit_log: None,
         })
     }
     pub(crate) fn api_url(&self) -> Result<String> {
@@ -146,6 +302,196 @@ impl Profile {
 
         bail!("Profile has no `server_base` or `server_identity`");
     }
+
+    /// Resolves the access token from `access_token_file` or `access_token_command`, in that
+    /// order. Errors only describe the source and never include the token itself.
+    pub(crate) fn access_token(&self) -> Result<Option<String>> {
+        if let Some(file) = &self.access_token_file {
+            let token = match read_to_string(file) {
+                Ok(token) => token,
+                Err(e) => bail!("Failed to read `access_token_file` `{file}`: {e}"),
+            };
+            return Ok(Some(token.trim().to_string()));
+        }
+
+        if let Some(command) = &self.access_token_command {
+            return run_access_token_command(command).map(Some);
+        }
+
+        Ok(None)
+    }
+
+    pub(crate) fn default_project(&self) -> Result<Option<Uuid>> {
+        match &self.default_project {
+            Some(project_id) => match Uuid::parse_str(project_id) {
+                Ok(project_id) => Ok(Some(project_id)),
+                Err(_) => bail!("Profile key \"default_project\": must be a valid project ID"),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Values are shown unless the profile says otherwise. An invalid value hides them, so a typo
+    /// never shows secrets, and `config validate` reports it.
+    pub(crate) fn reveal(&self) -> bool {
+        self.reveal
+            .as_deref()
+            .map(|r| util::string_to_bool(r).unwrap_or(false))
+            .unwrap_or(true)
+    }
+
+    /// Protected profiles require typing the profile name before anything is deleted or edited.
+    pub(crate) fn protected(&self) -> bool {
+        self.protected
+            .as_deref()
+            .is_some_and(|p| util::string_to_bool(p).unwrap_or(true))
+    }
+
+    pub(crate) fn timeout(&self) -> Result<Option<TimeDelta>> {
+        self.timeout
+            .as_deref()
+            .map(util::parse_duration)
+            .transpose()
+    }
+
+    /// How long deleted items are kept in the journal for `bws undo`. Zero disables the journal.
+    pub(crate) fn journal_retention(&self) -> Result<TimeDelta> {
+        match &self.journal_retention {
+            Some(retention) => util::parse_duration(retention),
+            None => Ok(TimeDelta::days(DEFAULT_JOURNAL_RETENTION_DAYS)),
+        }
+    }
+
+    /// Checks every key of the profile, returning a description of each problem found.
+    pub(crate) fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for key in ProfileKey::value_variants() {
+            if let Some(Err(e)) = key.profile_value(self).map(|v| key.validate_value(v)) {
+                problems.push(format!("{}: {}", key.name(), e));
+            }
+        }
+
+        if self.server_base.is_none()
+            && (self.server_api.is_none() || self.server_identity.is_none())
+        {
+            problems.push(
+                "server_base: must be set unless both `server_api` and `server_identity` are set"
+                    .to_string(),
+            );
+        }
+
+        if let Some(state_dir) = &self.state_dir {
+            if let Err(e) = check_writable(Path::new(state_dir)) {
+                problems.push(format!("state_dir: `{state_dir}` is not writable: {e}"));
+            }
+        }
+
+        for (key, file) in [
+            (ProfileKey::access_token_file, &self.access_token_file),
+            (ProfileKey::ca_cert_path, &self.ca_cert_path),
+        ] {
+            if let Some(file) = file {
+                if !Path::new(file).is_file() {
+                    problems.push(format!("{}: `{file}` doesn't exist", key.name()));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+/// Boolean keys are stored as strings, but also accept TOML booleans when written by hand.
+fn string_or_bool<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrBool {
+        String(String),
+        Bool(bool),
+    }
+
+    Ok(
+        Option::<StringOrBool>::deserialize(deserializer)?.map(|v| match v {
+            StringOrBool::String(s) => s,
+            StringOrBool::Bool(b) => b.to_string(),
+        }),
+    )
+}
+
+fn validate_value_enum<T: ValueEnum>(value: &str) -> Result<()> {
+    if T::from_str(value, true).is_ok() {
+        return Ok(());
+    }
+
+    let possible_values: Vec<String> = T::value_variants()
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_string())
+        .collect();
+    bail!("must be one of: {}", possible_values.join(", "));
+}
+
+fn validate_url(url: &str) -> Result<()> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        bail!("Server URL must start with http:// or https://, the provided URL is: `{url}`");
+    }
+
+    Ok(())
+}
+
+/// Checks that files can be created in `dir` without leaving anything behind. A directory that
+/// doesn't exist yet is only checked through the permissions of its closest existing ancestor, so
+/// nothing is ever written outside the state directory.
+fn check_writable(dir: &Path) -> std::io::Result<()> {
+    if dir.exists() {
+        // Anonymous, and removed when it's dropped
+        return tempfile::tempfile_in(dir).map(drop);
+    }
+
+    let Some(existing) = dir.ancestors().find(|d| d.exists()) else {
+        return Err(std::io::ErrorKind::NotFound.into());
+    };
+    let metadata = std::fs::metadata(existing)?;
+    if !metadata.is_dir() || metadata.permissions().readonly() {
+        return Err(std::io::ErrorKind::PermissionDenied.into());
+    }
+
+    Ok(())
+}
+
+fn run_access_token_command(command: &str) -> Result<String> {
+    let (shell, flag) = match std::env::consts::OS {
+        "windows" => ("powershell", "-c"),
+        _ => ("sh", "-c"),
+    };
+
+    // stdin and stderr are inherited so that helpers like `pass` can prompt for a passphrase
+    let output = match Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => bail!("Failed to execute `access_token_command`: {e}"),
+    };
+
+    if !output.status.success() {
+        bail!(
+            "`access_token_command` exited with status {}",
+            output.status.code().unwrap_or(1)
+        );
+    }
+
+    let Ok(token) = String::from_utf8(output.stdout) else {
+        bail!("`access_token_command` didn't output valid UTF-8");
+    };
+
+    Ok(token.trim().to_string())
 }
 
 impl Config {
@@ -159,7 +505,10 @@ impl Config {
         }
 
         if profile_defined {
-            bail!("The specified profile does not exist");
+            bail!(CliError::new(
+                ErrorCategory::NotFound,
+                "The specified profile does not exist"
+            ));
         }
 
         if let Some(profile) = self.profiles.get("default") {
@@ -214,4 +563,120 @@ mod tests {
             c.unwrap().profiles["default"].server_base.as_ref().unwrap()
         );
     }
+
+    #[test]
+    fn protected_accepts_toml_bool() {
+        let tmpfile = NamedTempFile::new().unwrap();
+        write!(
+            tmpfile.as_file(),
+            "[profiles.prod]
+        protected = true
+        reveal = \"false\"
+
+        [profiles.dev]
+        protected = false
+        "
+        )
+        .unwrap();
+
+        let config = load_config(Some(Path::new(tmpfile.as_ref())), true).unwrap();
+        assert!(config.profiles["prod"].protected());
+        assert!(!config.profiles["prod"].reveal());
+        assert!(!config.profiles["dev"].protected());
+        assert!(config.profiles["dev"].reveal());
+    }
+
+    #[test]
+    fn invalid_reveal_hides_values() {
+        let profile = Profile {
+            reveal: Some("flase".to_string()),
+            ..Default::default()
+        };
+        assert!(!profile.reveal());
+    }
+
+    #[test]
+    fn access_token_from_file() {
+        let tmpfile = NamedTempFile::new().unwrap();
+        writeln!(tmpfile.as_file(), "0.token:key").unwrap();
+
+        let profile = Profile {
+            access_token_file: Some(tmpfile.path().to_string_lossy().to_string()),
+            access_token_command: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some("0.token:key".to_string()),
+            profile.access_token().unwrap()
+        );
+    }
+
+    #[test]
+    fn access_token_missing_file_errors() {
+        let profile = Profile {
+            access_token_file: Some("non_existing".to_string()),
+            ..Default::default()
+        };
+        assert!(profile.access_token().is_err());
+    }
+
+    #[test]
+    fn access_token_not_configured() {
+        assert_eq!(None, Profile::default().access_token().unwrap());
+    }
+
+    #[test]
+    fn profile_validation() {
+        let profile = Profile {
+            server_base: Some("bitwarden.com".to_string()),
+            state_opt_out: Some("maybe".to_string()),
+            ..Default::default()
+        };
+        let problems = profile.validate();
+        assert_eq!(2, problems.len());
+        assert!(problems[0].starts_with("server_base:"));
+        assert!(problems[1].starts_with("state_opt_out:"));
+
+        let profile = Profile {
+            server_api: Some("https://api.bitwarden.com".to_string()),
+            server_identity: Some("https://identity.bitwarden.com".to_string()),
+            ..Default::default()
+        };
+        assert!(profile.validate().is_empty());
+    }
+
+    #[test]
+    fn profile_validation_of_defaults() {
+        let profile = Profile {
+            server_base: Some("https://bitwarden.com".to_string()),
+            output: Some("xml".to_string()),
+            color: Some("no".to_string()),
+            default_project: Some("not-a-uuid".to_string()),
+            reveal: Some("false".to_string()),
+            timeout: Some("30s".to_string()),
+            ..Default::default()
+        };
+        let problems = profile.validate();
+        assert_eq!(2, problems.len());
+        assert!(problems[0].starts_with("output:"));
+        assert!(problems[1].starts_with("default_project:"));
+    }
+
+    #[test]
+    fn profile_validation_requires_urls() {
+        let profile = Profile {
+            server_api: Some("https://api.bitwarden.com".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(1, profile.validate().len());
+    }
+
+    #[test]
+    fn state_dir_check_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+
+        check_writable(dir.path()).unwrap();
+        check_writable(&dir.path().join("not/created/yet")).unwrap();
+        assert_eq!(0, std::fs::read_dir(dir.path()).unwrap().count());
+    }
 }
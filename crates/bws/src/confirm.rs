@@ -0,0 +1,85 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use color_eyre::eyre::{bail, Result};
+use uuid::Uuid;
+
+use crate::{
+    error::{CliError, ErrorCategory},
+    util::exclude_from_timeouts,
+};
+
+/// How destructive operations are confirmed, from the `--yes` flag and the `protected` key of the
+/// profile in use.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Confirmation {
+    pub(crate) yes: bool,
+    /// The name of the profile in use, when it's protected.
+    pub(crate) protected_profile: Option<String>,
+}
+
+impl Confirmation {
+    /// Lists `items` by name and asks `question` about them, unless `--yes` was given.
+    pub(crate) fn confirm(&self, question: &str, items: &[(Uuid, String)]) -> Result<()> {
+        self.confirm_protected()?;
+
+        if self.yes {
+            return Ok(());
+        }
+
+        if !std::io::stdin().is_terminal() {
+            bail!(CliError::new(
+                ErrorCategory::Validation,
+                "Confirmation is required, but stdin isn't a terminal. Pass --yes to skip it."
+            ));
+        }
+
+        let mut prompt = String::new();
+        for (id, name) in items {
+            prompt.push_str(&format!("  {name} ({id})\n"));
+        }
+        prompt.push_str(&format!("{question} [y/N] "));
+
+        match ask(&prompt)?.to_lowercase().as_str() {
+            "y" | "yes" => Ok(()),
+            _ => bail!(CliError::new(ErrorCategory::Other, "Aborted.")),
+        }
+    }
+
+    /// Asks for the profile name before modifying anything in a protected profile. This can't be
+    /// skipped with `--yes`.
+    pub(crate) fn confirm_protected(&self) -> Result<()> {
+        let Some(profile) = &self.protected_profile else {
+            return Ok(());
+        };
+
+        if !std::io::stdin().is_terminal() {
+            bail!(CliError::new(
+                ErrorCategory::Permission,
+                format!(
+                    "Profile `{profile}` is protected and can only be modified from a terminal."
+                )
+            ));
+        }
+
+        let prompt = format!("Profile `{profile}` is protected. Type its name to continue: ");
+        if ask(&prompt)? != *profile {
+            bail!(CliError::new(
+                ErrorCategory::Permission,
+                "The profile name didn't match. Aborted."
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the prompt to stderr, so it doesn't mix with the output, and reads a line from stdin.
+/// Waiting for the answer doesn't count towards the timeout of the command.
+fn ask(prompt: &str) -> Result<String> {
+    eprint!("{prompt}");
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    exclude_from_timeouts(|| std::io::stdin().lock().read_line(&mut answer))?;
+    Ok(answer.trim().to_string())
+}
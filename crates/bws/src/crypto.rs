@@ -0,0 +1,66 @@
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use color_eyre::eyre::{eyre, Result};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+pub(crate) const KEY_LEN: usize = 32;
+pub(crate) const NONCE_LEN: usize = 24;
+pub(crate) const TAG_LEN: usize = 16;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Encrypts `plaintext` with XChaCha20-Poly1305 and a random nonce, and appends the nonce and the
+/// ciphertext, which ends with the authentication tag, to `header`. The header is authenticated as
+/// associated data, so it can't be changed either.
+pub(crate) fn seal(mut header: Vec<u8>, plaintext: &[u8], key: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| eyre!("Failed to encrypt"))?;
+
+    header.extend_from_slice(&nonce);
+    header.extend_from_slice(&ciphertext);
+
+    Ok(header)
+}
+
+/// Checks the tag of data written by [`seal`] with a header of `header_len` bytes and decrypts it.
+/// Returns `None` if the key is wrong or the data was modified.
+pub(crate) fn unseal(sealed: &[u8], header_len: usize, key: &[u8; KEY_LEN]) -> Option<Vec<u8>> {
+    if sealed.len() < header_len + NONCE_LEN + TAG_LEN {
+        return None;
+    }
+
+    let (header, content) = sealed.split_at(header_len);
+    let (nonce, ciphertext) = content.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .ok()
+}
+
+/// Derives a key from a secret that already has enough entropy, such as an access token, so no
+/// slow key derivation is needed.
+pub(crate) fn derive_key_from_secret(secret: &str, purpose: &str) -> Result<[u8; KEY_LEN]> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(secret.as_bytes())?;
+    mac.update(purpose.as_bytes());
+
+    Ok(mac.finalize().into_bytes().into())
+}
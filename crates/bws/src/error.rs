@@ -0,0 +1,252 @@
+use color_eyre::{eyre::bail, Report, Result};
+use serde::Serialize;
+
+/// A coarse classification of errors, used to tell authentication problems apart from network or
+/// server issues. Each category maps to a stable exit code, see [`ErrorCategory::exit_code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorCategory {
+    Auth,
+    NotFound,
+    Permission,
+    Network,
+    Validation,
+    PartialFailure,
+    Server,
+    /// Not an error: a check found something, such as `diff` finding differences.
+    Findings,
+    Other,
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let category = match self {
+            ErrorCategory::Auth => "authentication",
+            ErrorCategory::NotFound => "not found",
+            ErrorCategory::Permission => "permission",
+            ErrorCategory::Network => "network",
+            ErrorCategory::Validation => "validation",
+            ErrorCategory::PartialFailure => "partial failure",
+            ErrorCategory::Server => "server",
+            ErrorCategory::Findings => "findings",
+            ErrorCategory::Other => "other",
+        };
+        write!(f, "{category}")
+    }
+}
+
+impl From<&bitwarden::Error> for ErrorCategory {
+    fn from(error: &bitwarden::Error) -> Self {
+        match error {
+            bitwarden::Error::NotAuthenticated
+            | bitwarden::Error::IdentityFail(_)
+            | bitwarden::Error::AccessTokenInvalid(_)
+            | bitwarden::Error::InvalidStateFile
+            | bitwarden::Error::InvalidStateFileVersion => ErrorCategory::Auth,
+            bitwarden::Error::Reqwest(e) => match e.status() {
+                Some(status) => ErrorCategory::from_status(status.as_u16()),
+                None => ErrorCategory::Network,
+            },
+            bitwarden::Error::ResponseContent { status, .. } => {
+                ErrorCategory::from_status(status.as_u16())
+            }
+            bitwarden::Error::ValidationError(_) => ErrorCategory::Validation,
+            _ => ErrorCategory::Other,
+        }
+    }
+}
+
+impl ErrorCategory {
+    /// The process exit code for this category. These are part of the CLI's interface and are
+    /// documented in the README, so existing values must not change. Exit code 2 is used by the
+    /// argument parser for usage errors.
+    pub(crate) fn exit_code(self) -> i32 {
+        match self {
+            ErrorCategory::Other => 1,
+            ErrorCategory::Auth => 3,
+            ErrorCategory::NotFound => 4,
+            ErrorCategory::Permission => 5,
+            ErrorCategory::Network => 6,
+            ErrorCategory::Validation => 7,
+            ErrorCategory::PartialFailure => 8,
+            ErrorCategory::Server => 9,
+            ErrorCategory::Findings => 10,
+        }
+    }
+
+    /// Finds the most specific category for an error by looking through its chain of causes.
+    pub(crate) fn from_report(report: &Report) -> Self {
+        for cause in report.chain() {
+            if let Some(e) = cause.downcast_ref::<CliError>() {
+                return e.category;
+            }
+            if let Some(e) = cause.downcast_ref::<bitwarden::Error>() {
+                return e.into();
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                return match e.kind() {
+                    std::io::ErrorKind::NotFound => ErrorCategory::NotFound,
+                    std::io::ErrorKind::PermissionDenied => ErrorCategory::Permission,
+                    _ => ErrorCategory::Other,
+                };
+            }
+            if cause.is::<uuid::Error>()
+                || cause.is::<toml::de::Error>()
+                || cause.is::<serde_json::Error>()
+            {
+                return ErrorCategory::Validation;
+            }
+        }
+
+        ErrorCategory::Other
+    }
+
+    fn from_status(status: u16) -> Self {
+        match status {
+            401 => ErrorCategory::Auth,
+            403 => ErrorCategory::Permission,
+            404 => ErrorCategory::NotFound,
+            400..=499 => ErrorCategory::Validation,
+            500..=599 => ErrorCategory::Server,
+            _ => ErrorCategory::Other,
+        }
+    }
+}
+
+/// An error with an explicit category, for failures that don't come from the SDK.
+#[derive(Debug)]
+pub(crate) struct CliError {
+    category: ErrorCategory,
+    message: String,
+    details: Vec<String>,
+}
+
+impl CliError {
+    pub(crate) fn new(category: ErrorCategory, message: impl Into<String>) -> Self {
+        CliError {
+            category,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Adds details, such as the individual failures of a bulk operation, to the JSON error output.
+    pub(crate) fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Prints the items a bulk command failed on, with the error of each, and returns a partial
+/// failure with `message` if there were any. `noun` is what the items are, such as `secret`.
+pub(crate) fn report_partial_failure<T: std::fmt::Display>(
+    noun: &str,
+    message: impl Into<String>,
+    failed: &[(T, String)],
+) -> Result<()> {
+    match failed.len() {
+        0 => return Ok(()),
+        1 => eprintln!("1 {noun} had an error:"),
+        count => eprintln!("{count} {noun}s had errors:"),
+    }
+
+    let details: Vec<String> = failed
+        .iter()
+        .map(|(item, error)| format!("{item}: {error}"))
+        .collect();
+    for detail in &details {
+        eprintln!("{detail}");
+    }
+
+    bail!(CliError::new(ErrorCategory::PartialFailure, message).with_details(details))
+}
+
+/// The error written to stderr when JSON output is selected.
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    code: ErrorCategory,
+    message: String,
+    details: Vec<String>,
+}
+
+impl ErrorResponse {
+    fn new(report: &Report) -> Self {
+        let cli_error = report.chain().find_map(|e| e.downcast_ref::<CliError>());
+
+        // The causes of an error are the most useful details, followed by any attached to it
+        let mut details: Vec<String> = report.chain().skip(1).map(|e| e.to_string()).collect();
+        if let Some(e) = cli_error {
+            details.extend(e.details.iter().cloned());
+        }
+
+        ErrorResponse {
+            code: ErrorCategory::from_report(report),
+            message: report.to_string(),
+            details,
+        }
+    }
+}
+
+/// Writes the error to stderr, as JSON when `json` is set, and returns the exit code to use.
+pub(crate) fn report_error(report: Report, json: bool) -> i32 {
+    if json {
+        match serde_json::to_string(&ErrorResponse::new(&report)) {
+            Ok(error) => eprintln!("{error}"),
+            Err(_) => eprintln!("Error: {report:?}"),
+        }
+    } else {
+        eprintln!("Error: {report:?}");
+    }
+
+    ErrorCategory::from_report(&report).exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+
+    #[test]
+    fn categorizes_through_error_context() {
+        use color_eyre::eyre::WrapErr;
+
+        let report = Err::<(), _>(CliError::new(ErrorCategory::Auth, "Missing access token"))
+            .wrap_err("Failed to log in")
+            .unwrap_err();
+        assert_eq!(ErrorCategory::Auth, ErrorCategory::from_report(&report));
+        assert_eq!(3, ErrorCategory::from_report(&report).exit_code());
+
+        let report = eyre!("Something else");
+        assert_eq!(ErrorCategory::Other, ErrorCategory::from_report(&report));
+    }
+
+    #[test]
+    fn json_error_includes_details() {
+        let report = Report::new(
+            CliError::new(ErrorCategory::PartialFailure, "Errors when deleting.")
+                .with_details(vec!["id: not found".to_string()]),
+        );
+
+        let json = serde_json::to_value(ErrorResponse::new(&report)).unwrap();
+        assert_eq!("partial_failure", json["code"]);
+        assert_eq!("Errors when deleting.", json["message"]);
+        assert_eq!("id: not found", json["details"][0]);
+    }
+
+    #[test]
+    fn status_codes_are_categorized() {
+        assert_eq!(ErrorCategory::Auth, ErrorCategory::from_status(401));
+        assert_eq!(ErrorCategory::NotFound, ErrorCategory::from_status(404));
+        assert_eq!(ErrorCategory::Validation, ErrorCategory::from_status(400));
+        assert_eq!(ErrorCategory::Server, ErrorCategory::from_status(502));
+    }
+}
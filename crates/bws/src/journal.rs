@@ -0,0 +1,372 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    backup::{BackupProject, BackupSecret},
+    crypto,
+    error::{CliError, ErrorCategory},
+    state::get_state_dir,
+    util::{create_private_dir, write_private_file},
+};
+
+const JOURNAL_DIRECTORY: &str = "journal";
+const ENTRY_EXTENSION: &str = "bwsj";
+
+const MAGIC: &[u8; 6] = b"BWSJNL";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+
+/// The projects and secrets removed by a single delete command, with everything needed to create
+/// them again.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JournalEntry {
+    pub(crate) id: Uuid,
+    pub(crate) deleted: DateTime<Utc>,
+    pub(crate) organization_id: Uuid,
+    pub(crate) projects: Vec<BackupProject>,
+    pub(crate) secrets: Vec<BackupSecret>,
+    /// The secrets that were in the deleted projects. They aren't deleted with the project, so
+    /// undoing only moves them back into it.
+    #[serde(default)]
+    pub(crate) detached: Vec<DetachedSecret>,
+}
+
+/// A secret that was left without a project when its project was deleted.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DetachedSecret {
+    pub(crate) id: Uuid,
+    pub(crate) project_id: Uuid,
+}
+
+/// A journal entry as shown by `bws journal list`, which never includes secret values.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JournalSummary {
+    pub(crate) id: Uuid,
+    pub(crate) deleted: DateTime<Utc>,
+    pub(crate) projects: Vec<String>,
+    pub(crate) secrets: Vec<String>,
+}
+
+impl From<&JournalEntry> for JournalSummary {
+    fn from(entry: &JournalEntry) -> Self {
+        JournalSummary {
+            id: entry.id,
+            deleted: entry.deleted,
+            projects: entry.projects.iter().map(|p| p.name.clone()).collect(),
+            secrets: entry.secrets.iter().map(|s| s.key.clone()).collect(),
+        }
+    }
+}
+
+/// The local journal of deleted items for a single access token.
+///
+/// Each entry is a separate file, encrypted and authenticated with a key derived from the access
+/// token, so the journal is only readable with the token that made the deletion.
+pub(crate) struct Journal {
+    dir: PathBuf,
+    key: [u8; crypto::KEY_LEN],
+    retention: TimeDelta,
+}
+
+impl Journal {
+    /// Opens the journal in the state directory, or returns `None` if `retention` is zero, which
+    /// disables it.
+    pub(crate) fn open(
+        state_dir: Option<PathBuf>,
+        access_token_id: Uuid,
+        access_token: &str,
+        retention: TimeDelta,
+    ) -> Result<Option<Journal>> {
+        if retention <= TimeDelta::zero() {
+            return Ok(None);
+        }
+
+        Ok(Some(Journal {
+            dir: get_state_dir(state_dir)?
+                .join(JOURNAL_DIRECTORY)
+                .join(access_token_id.to_string()),
+            key: crypto::derive_key_from_secret(access_token, "bws deletion journal")?,
+            retention,
+        }))
+    }
+
+    /// Writes a new entry before the items are deleted, and prunes entries past the retention.
+    pub(crate) fn record(
+        &self,
+        organization_id: Uuid,
+        projects: Vec<BackupProject>,
+        secrets: Vec<BackupSecret>,
+        detached: Vec<DetachedSecret>,
+    ) -> Result<Uuid> {
+        let entry = JournalEntry {
+            id: Uuid::new_v4(),
+            deleted: Utc::now(),
+            organization_id,
+            projects,
+            secrets,
+            detached,
+        };
+
+        create_private_dir(&self.dir)?;
+        self.write(&entry)
+            .wrap_err("Failed to write the deletion journal, nothing was deleted")?;
+
+        if let Err(e) = self.prune() {
+            eprintln!("Warning: Failed to prune the deletion journal: {e}");
+        }
+
+        Ok(entry.id)
+    }
+
+    /// Replaces an existing entry, for keeping the items an undo couldn't recreate.
+    pub(crate) fn write(&self, entry: &JournalEntry) -> Result<()> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(FORMAT_VERSION);
+
+        let sealed = crypto::seal(header, &serde_json::to_vec(entry)?, &self.key)?;
+        write_private_file(&self.entry_path(entry.id), sealed)
+    }
+
+    /// Returns the entries that are still within the retention, newest first. Entries that can't
+    /// be decrypted with this access token are skipped.
+    pub(crate) fn list(&self) -> Result<Vec<JournalEntry>> {
+        let mut entries = Vec::new();
+        for path in self.entry_paths()? {
+            match self.read(&path) {
+                Ok(entry) if Utc::now() - entry.deleted < self.retention => entries.push(entry),
+                Ok(_) => (),
+                Err(e) => eprintln!("Warning: Skipping {}: {e}", path.display()),
+            }
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.deleted));
+
+        Ok(entries)
+    }
+
+    /// Returns the entry with the given ID, or the most recent one.
+    pub(crate) fn get(&self, id: Option<Uuid>) -> Result<JournalEntry> {
+        let entry = match id {
+            Some(id) => self
+                .list()?
+                .into_iter()
+                .find(|e| e.id == id)
+                .ok_or_else(|| {
+                    CliError::new(
+                        ErrorCategory::NotFound,
+                        format!("No journal entry with ID {id}"),
+                    )
+                })?,
+            None => self.list()?.into_iter().next().ok_or_else(|| {
+                CliError::new(ErrorCategory::NotFound, "The deletion journal is empty")
+            })?,
+        };
+
+        Ok(entry)
+    }
+
+    /// Keeps only the items of an entry that were actually deleted, and removes the entry when
+    /// nothing was. Returns whether anything is left to undo.
+    pub(crate) fn reconcile(&self, id: Uuid, deleted: &[Uuid]) -> Result<bool> {
+        let mut entry = self.read(&self.entry_path(id))?;
+        entry.projects.retain(|p| deleted.contains(&p.id));
+        entry.secrets.retain(|s| deleted.contains(&s.id));
+        entry.detached.retain(|s| deleted.contains(&s.project_id));
+
+        if entry.projects.is_empty() && entry.secrets.is_empty() {
+            self.remove(id)?;
+            return Ok(false);
+        }
+
+        self.write(&entry)?;
+        Ok(true)
+    }
+
+    pub(crate) fn remove(&self, id: Uuid) -> Result<()> {
+        std::fs::remove_file(self.entry_path(id))?;
+        Ok(())
+    }
+
+    /// Removes entries older than the retention. The file modification time is used, so entries
+    /// written with another access token are pruned as well.
+    pub(crate) fn prune(&self) -> Result<()> {
+        for path in self.entry_paths()? {
+            let modified: DateTime<Utc> = std::fs::metadata(&path)?.modified()?.into();
+            if Utc::now() - modified >= self.retention {
+                std::fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<JournalEntry> {
+        let sealed = std::fs::read(path)?;
+        if !sealed.starts_with(MAGIC) || sealed.get(MAGIC.len()) != Some(&FORMAT_VERSION) {
+            bail!("not a bws journal entry");
+        }
+
+        match crypto::unseal(&sealed, HEADER_LEN, &self.key) {
+            Some(plaintext) => Ok(serde_json::from_slice(&plaintext)?),
+            None => bail!("written with another access token, or corrupted"),
+        }
+    }
+
+    fn entry_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.{ENTRY_EXTENSION}"))
+    }
+
+    fn entry_paths(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == ENTRY_EXTENSION) {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn open(state_dir: &Path, access_token: &str) -> Journal {
+        Journal::open(
+            Some(state_dir.to_owned()),
+            Uuid::nil(),
+            access_token,
+            TimeDelta::days(30),
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    fn secret() -> BackupSecret {
+        BackupSecret {
+            id: Uuid::new_v4(),
+            project_id: None,
+            key: "DB_PASSWORD".to_string(),
+            value: "hunter2".to_string(),
+            note: "rotate monthly".to_string(),
+        }
+    }
+
+    #[test]
+    fn round_trip_without_plaintext_on_disk() {
+        let dir = tempdir().unwrap();
+        let journal = open(dir.path(), "token");
+
+        let id = journal
+            .record(Uuid::nil(), Vec::new(), vec![secret()], Vec::new())
+            .unwrap();
+
+        let entry = journal.get(None).unwrap();
+        assert_eq!(id, entry.id);
+        assert_eq!(vec![secret().key], JournalSummary::from(&entry).secrets);
+        assert_eq!("hunter2", entry.secrets[0].value);
+
+        let raw = std::fs::read(journal.entry_path(id)).unwrap();
+        for plaintext in ["hunter2", "DB_PASSWORD", "rotate monthly"] {
+            assert!(!raw
+                .windows(plaintext.len())
+                .any(|w| w == plaintext.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn other_access_token_cant_read() {
+        let dir = tempdir().unwrap();
+        open(dir.path(), "token")
+            .record(Uuid::nil(), Vec::new(), vec![secret()], Vec::new())
+            .unwrap();
+
+        assert!(open(dir.path(), "other token").list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reconcile_keeps_only_deleted_items() {
+        let dir = tempdir().unwrap();
+        let journal = open(dir.path(), "token");
+        let (deleted, failed) = (secret(), secret());
+        let deleted_id = deleted.id;
+
+        let id = journal
+            .record(Uuid::nil(), Vec::new(), vec![deleted, failed], Vec::new())
+            .unwrap();
+        assert!(journal.reconcile(id, &[deleted_id]).unwrap());
+        let secrets = journal.get(Some(id)).unwrap().secrets;
+        assert_eq!(
+            vec![deleted_id],
+            secrets.iter().map(|s| s.id).collect::<Vec<_>>()
+        );
+
+        assert!(!journal.reconcile(id, &[]).unwrap());
+        assert!(journal.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reconcile_keeps_secrets_of_deleted_projects() {
+        let dir = tempdir().unwrap();
+        let journal = open(dir.path(), "token");
+        let projects: Vec<BackupProject> = ["deleted", "failed"]
+            .into_iter()
+            .map(|name| BackupProject {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+            })
+            .collect();
+        let detached = projects
+            .iter()
+            .map(|p| DetachedSecret {
+                id: Uuid::new_v4(),
+                project_id: p.id,
+            })
+            .collect();
+        let deleted_id = projects[0].id;
+
+        let id = journal
+            .record(Uuid::nil(), projects, Vec::new(), detached)
+            .unwrap();
+        assert!(journal.reconcile(id, &[deleted_id]).unwrap());
+        let entry = journal.get(Some(id)).unwrap();
+        assert_eq!(1, entry.projects.len());
+        assert_eq!(
+            vec![deleted_id],
+            entry
+                .detached
+                .iter()
+                .map(|s| s.project_id)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn zero_retention_disables_the_journal() {
+        let dir = tempdir().unwrap();
+        let journal = Journal::open(
+            Some(dir.path().to_owned()),
+            Uuid::nil(),
+            "token",
+            TimeDelta::zero(),
+        )
+        .unwrap();
+
+        assert!(journal.is_none());
+    }
+}
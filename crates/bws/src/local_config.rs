@@ -0,0 +1,137 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    cli::LOCAL_CONFIG_FILENAME,
+    error::{CliError, ErrorCategory},
+};
+
+/// Repository-local settings read from a `.bws.toml` found in the current directory or one of its
+/// parents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LocalConfig {
+    pub profile: Option<String>,
+    pub project: Option<Uuid>,
+    #[serde(default)]
+    pub stages: HashMap<String, Uuid>,
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+impl LocalConfig {
+    /// Resolves the project to use, preferring an explicit project ID, then a named stage and
+    /// finally the default project.
+    pub(crate) fn resolve_project(
+        &self,
+        project_id: Option<Uuid>,
+        stage: Option<&str>,
+    ) -> Result<Option<Uuid>> {
+        if project_id.is_some() {
+            return Ok(project_id);
+        }
+
+        if let Some(stage) = stage {
+            return match self.stages.get(stage) {
+                Some(project_id) => Ok(Some(*project_id)),
+                None => bail!(CliError::new(
+                    ErrorCategory::NotFound,
+                    format!("Stage `{stage}` is not defined in {LOCAL_CONFIG_FILENAME}")
+                )),
+            };
+        }
+
+        Ok(self.project)
+    }
+}
+
+fn find_local_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(LOCAL_CONFIG_FILENAME))
+        .find(|file| file.is_file())
+}
+
+pub(crate) fn load_local_config() -> Result<LocalConfig> {
+    let Ok(current_dir) = std::env::current_dir() else {
+        return Ok(LocalConfig::default());
+    };
+
+    let Some(file) = find_local_config(&current_dir) else {
+        return Ok(LocalConfig::default());
+    };
+
+    match toml::from_str(&read_to_string(&file)?) {
+        Ok(config) => Ok(config),
+        Err(e) => bail!("Failed to parse {}: {}", file.display(), e.message()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn local_config_found_in_parent() {
+        let dir = tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join(LOCAL_CONFIG_FILENAME), "profile = \"work\"").unwrap();
+
+        assert_eq!(
+            Some(dir.path().join(LOCAL_CONFIG_FILENAME)),
+            find_local_config(&nested)
+        );
+    }
+
+    #[test]
+    fn local_config_parses_stages() {
+        let config: LocalConfig = toml::from_str(
+            "profile = \"work\"
+            project = \"759130d0-29dd-48bd-831a-e3bdbafeeb6e\"
+            required = [\"DB_PASSWORD\"]
+
+            [stages]
+            prod = \"e5e7b8a3-5a0f-4c4e-9f3a-b2d4e8e8c3a1\"
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(Some("work".to_string()), config.profile);
+        assert_eq!(vec!["DB_PASSWORD".to_string()], config.required);
+        assert_eq!(
+            Some(Uuid::parse_str("e5e7b8a3-5a0f-4c4e-9f3a-b2d4e8e8c3a1").unwrap()),
+            config.resolve_project(None, Some("prod")).unwrap()
+        );
+        assert_eq!(
+            Some(Uuid::parse_str("759130d0-29dd-48bd-831a-e3bdbafeeb6e").unwrap()),
+            config.resolve_project(None, None).unwrap()
+        );
+        assert!(config.resolve_project(None, Some("dev")).is_err());
+    }
+
+    #[test]
+    fn explicit_project_overrides_stage() {
+        let project_id = Uuid::new_v4();
+        let config = LocalConfig {
+            project: Some(Uuid::new_v4()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Some(project_id),
+            config
+                .resolve_project(Some(project_id), Some("prod"))
+                .unwrap()
+        );
+    }
+}
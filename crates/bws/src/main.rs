@@ -1,115 +1,335 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{ffi::OsString, path::PathBuf, str::FromStr};
 
 use bitwarden::{
     auth::{login::AccessTokenLoginRequest, AccessToken},
     ClientSettings,
 };
 use bitwarden_cli::install_color_eyre;
-use clap::{CommandFactory, Parser};
-use color_eyre::eyre::{bail, Result};
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, ValueEnum};
+use color_eyre::eyre::{bail, Result, WrapErr};
 use config::Profile;
+use error::{CliError, ErrorCategory};
 use log::error;
 use render::OutputSettings;
 
+mod agent;
+mod alias;
+mod audit;
+mod backup;
 mod cli;
 mod command;
+mod completion;
 mod config;
+mod confirm;
+mod crypto;
+mod error;
+mod journal;
+mod local_config;
+mod metadata;
+mod procfile;
 mod render;
 mod state;
+#[cfg(test)]
+mod test_util;
+mod tui;
 mod util;
 
 use crate::cli::*;
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
+async fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    process_commands().await
+    let mut matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Errors are only written as JSON when it was asked for, either explicitly or by the profile
+    let json_requested = |matches: &ArgMatches, cli: &Cli| {
+        matches.value_source("output") != Some(ValueSource::DefaultValue)
+            && cli.output == Output::JSON
+    };
+
+    match expand_alias(&cli) {
+        Ok(Some(args)) => {
+            matches = Cli::command().get_matches_from(args);
+            cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        }
+        Ok(None) => (),
+        Err(report) => {
+            std::process::exit(error::report_error(report, json_requested(&matches, &cli)))
+        }
+    }
+
+    let mut json_errors = json_requested(&matches, &cli);
+
+    if let Err(report) = process_commands(&matches, cli, &mut json_errors).await {
+        std::process::exit(error::report_error(report, json_errors));
+    }
+}
+
+/// Replaces an unknown subcommand defined in the `[aliases]` table of the config file with its
+/// arguments. The result isn't expanded again, so an alias can't refer to another alias.
+fn expand_alias(cli: &Cli) -> Result<Option<Vec<OsString>>> {
+    let Some(Commands::External(external)) = &cli.command else {
+        return Ok(None);
+    };
+
+    let config = config::load_config(cli.config_file.as_deref(), cli.config_file.is_some())?;
+    let Some(alias) = config.aliases.get(&external[0]) else {
+        return Ok(None);
+    };
+
+    // The subcommand and everything after it are the last arguments, as clap passes them as is
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    args.truncate(args.len() - external.len());
+    args.extend(
+        alias
+            .expand(&external[0], &external[1..])?
+            .into_iter()
+            .map(OsString::from),
+    );
+
+    Ok(Some(args))
 }
 
 #[allow(clippy::comparison_chain)]
-async fn process_commands() -> Result<()> {
-    let cli = Cli::parse();
+async fn process_commands(matches: &ArgMatches, cli: Cli, json_errors: &mut bool) -> Result<()> {
     let color = cli.color;
 
     install_color_eyre(color)?;
 
+    let local_config = local_config::load_local_config()?;
+    let cli_profile = cli.profile.or_else(|| local_config.profile.clone());
+
     let Some(command) = cli.command else {
         let mut cmd = Cli::command();
         eprintln!("{}", cmd.render_help().ansi());
         std::process::exit(1);
     };
 
-    // These commands don't require authentication, so we process them first
+    // These commands don't use the access token of the selected profile, so we process them first
     match command {
-        Commands::Completions { shell } => {
-            return command::completions(shell);
+        Commands::Completions { shell, dynamic } => {
+            return command::completions(shell, dynamic);
+        }
+        Commands::Complete {
+            shell,
+            index,
+            words,
+        } => {
+            return completion::complete(shell, index, words, cli_profile);
+        }
+        Commands::Config { cmd: Some(cmd), .. } => {
+            return command::config::process_command(
+                cmd,
+                matches,
+                &local_config,
+                OutputSettings::new(cli.output, color),
+            );
         }
         Commands::Config {
+            cmd: None,
             name,
             value,
             delete,
         } => {
-            return command::config(
+            return command::config::update(
                 name,
                 value,
                 delete,
-                cli.profile,
+                cli_profile,
                 cli.access_token,
                 cli.config_file,
             );
         }
+        Commands::State { cmd } => {
+            let profile = get_config_profile(
+                &cli.server_url,
+                &cli_profile,
+                &cli.config_file,
+                cli.access_token.as_deref(),
+            )?;
+            return command::state::process_command(
+                cmd,
+                profile.and_then(|p| p.state_dir).map(Into::into),
+                OutputSettings::new(cli.output, color),
+            );
+        }
+        Commands::Plugins { cmd } => {
+            return command::plugin::process_command(cmd, OutputSettings::new(cli.output, color));
+        }
+        Commands::Audit { cmd } => {
+            let profile = get_config_profile(
+                &cli.server_url,
+                &cli_profile,
+                &cli.config_file,
+                cli.access_token.as_deref(),
+            )?;
+            return command::audit::process_command(
+                cmd,
+                profile.and_then(|p| p.audit_log).map(Into::into),
+            );
+        }
+        Commands::Migrate {
+            from_profile,
+            to_profile,
+            mapping_file,
+            dry_run,
+        } => {
+            let source_profile = load_profile(&from_profile, &cli.config_file)?;
+            let target_profile = load_profile(&to_profile, &cli.config_file)?;
+            // The HTTP client reads the proxy from the environment once per process
+            if source_profile.http_proxy != target_profile.http_proxy {
+                bail!(CliError::new(
+                    ErrorCategory::Validation,
+                    format!("Profiles `{from_profile}` and `{to_profile}` have a different `http_proxy`, which can't be used in the same command")
+                ));
+            }
+
+            let (source, source_organization_id) =
+                login_with_profile(&from_profile, &source_profile, cli.allow_insecure_state)
+                    .await?;
+            let (target, target_organization_id) =
+                login_with_profile(&to_profile, &target_profile, cli.allow_insecure_state).await?;
+            if let Some(path) = &target_profile.audit_log {
+                audit::init(audit::AuditLog {
+                    path: path.into(),
+                    profile: to_profile.clone(),
+                    command: command_name(matches),
+                });
+            }
+
+            return command::migrate::migrate(
+                command::migrate::MigrateCommandModel {
+                    source,
+                    source_organization_id,
+                    target,
+                    target_organization_id,
+                    mapping_file,
+                    dry_run,
+                },
+                confirm::Confirmation {
+                    yes: cli.yes,
+                    protected_profile: target_profile.protected().then_some(to_profile),
+                },
+            )
+            .await;
+        }
         _ => (),
     }
 
-    let access_token = match cli.access_token {
-        Some(key) => key,
-        None => bail!("Missing access token"),
-    };
+    // Unknown subcommands are reported before asking for the access token
+    if let Commands::External(args) = &command {
+        command::plugin::find_plugin(&args[0])?;
+    }
+
+    let access_token = get_access_token(
+        cli.access_token,
+        cli.access_token_stdin,
+        &cli_profile,
+        &cli.config_file,
+    )?;
     let access_token_obj: AccessToken = access_token.parse()?;
 
     let profile = get_config_profile(
         &cli.server_url,
-        &cli.profile,
+        &cli_profile,
         &cli.config_file,
-        &access_token,
+        Some(&access_token),
     )?;
 
-    let settings = profile
-        .clone()
-        .map(|p| -> Result<_> {
-            Ok(ClientSettings {
-                identity_url: p.identity_url()?,
-                api_url: p.api_url()?,
-                ..Default::default()
-            })
-        })
-        .transpose()?;
-
-    let state_file = match get_state_opt_out(&profile) {
-        true => None,
-        false => match state::get_state_file(
-            profile.and_then(|p| p.state_dir).map(Into::into),
-            access_token_obj.access_token_id.to_string(),
-        ) {
-            Ok(state_file) => Some(state_file),
-            Err(e) => {
-                eprintln!("Warning: {}\nRetrieving the state file failed. Attempting to continue without using state. Please set \"state_dir\" in your config file to avoid authentication limits.", e);
-                None
-            }
-        },
+    let profile_defaults = profile.clone().unwrap_or_default();
+    let output = flag_or_profile_default(
+        matches,
+        "output",
+        cli.output,
+        profile_defaults.output.as_deref(),
+    )?;
+    if profile_defaults.output.is_some() {
+        *json_errors = output == Output::JSON;
+    }
+    let color =
+        flag_or_profile_default(matches, "color", color, profile_defaults.color.as_deref())?;
+    let reveal = cli.reveal || profile_defaults.reveal();
+    let default_project = profile_defaults.default_project()?;
+    let timeout = profile_defaults.timeout()?;
+    let _ca_dir = apply_network_settings(&profile_defaults)?;
+
+    let settings = get_client_settings(&profile)?;
+    let (api_url, identity_url) = (settings.api_url.clone(), settings.identity_url.clone());
+
+    let state_file = get_state_file(&profile, &access_token_obj, cli.allow_insecure_state);
+
+    let client = bitwarden::Client::new(Some(settings));
+
+    let output_settings = OutputSettings {
+        reveal,
+        ..OutputSettings::new(output, color)
+    };
+
+    let (profile_name, profile_source) = match &cli.server_url {
+        Some(_) => (
+            "none".to_string(),
+            command::config::value_source(matches, "server_url"),
+        ),
+        None => command::config::resolve_profile_name(
+            matches,
+            &local_config,
+            &config::load_config(cli.config_file.as_deref(), cli.config_file.is_some())?,
+            Some(access_token_obj.access_token_id.to_string()),
+        ),
     };
+    let confirmation = confirm::Confirmation {
+        yes: cli.yes,
+        protected_profile: profile_defaults.protected().then(|| profile_name.clone()),
+    };
+    if let Some(path) = &profile_defaults.audit_log {
+        audit::init(audit::AuditLog {
+            path: path.into(),
+            profile: profile_name.clone(),
+            command: command_name(matches),
+        });
+    }
+
+    let journal = journal::Journal::open(
+        profile_defaults.state_dir.clone().map(Into::into),
+        access_token_obj.access_token_id,
+        &access_token,
+        profile_defaults.journal_retention()?,
+    )?;
 
-    let client = bitwarden::Client::new(settings);
+    if let Commands::Journal { cmd } = command {
+        return command::journal::process_command(cmd, journal, output_settings);
+    }
+
+    if let Commands::Status { check } = command {
+        return command::status::status(
+            client,
+            command::status::StatusCommandModel {
+                access_token,
+                access_token_id: access_token_obj.access_token_id,
+                api_url,
+                identity_url,
+                profile: profile_name,
+                profile_source,
+                state_file,
+                check,
+                timeout,
+            },
+            output_settings,
+        )
+        .await;
+    }
 
     // Load session or return if no session exists
-    let _ = client
-        .auth()
-        .login_access_token(&AccessTokenLoginRequest {
-            access_token,
+    let _ = util::with_timeout(
+        timeout,
+        client.auth().login_access_token(&AccessTokenLoginRequest {
+            access_token: access_token.clone(),
             state_file,
-        })
-        .await?;
+        }),
+    )
+    .await??;
 
     let organization_id = match client.internal.get_access_token_organization() {
         Some(id) => id,
@@ -119,33 +339,64 @@ async fn process_commands() -> Result<()> {
         }
     };
 
-    let output_settings = OutputSettings::new(cli.output, color);
+    completion::init(profile_name.clone(), organization_id);
 
     // And finally we process all the commands which require authentication
     match command {
         Commands::Project { cmd } => {
-            command::project::process_command(cmd, client, organization_id, output_settings).await
+            util::with_timeout(
+                timeout,
+                command::project::process_command(
+                    cmd,
+                    client,
+                    organization_id,
+                    output_settings,
+                    confirmation,
+                    journal,
+                ),
+            )
+            .await?
         }
 
         Commands::Secret { cmd } => {
-            command::secret::process_command(cmd, client, organization_id, output_settings).await
+            util::with_timeout(
+                timeout,
+                command::secret::process_command(
+                    cmd,
+                    client,
+                    organization_id,
+                    output_settings,
+                    confirmation,
+                    journal,
+                ),
+            )
+            .await?
         }
 
         Commands::Run {
             command,
             shell,
             no_inherit_env,
-            project_id,
-            uuids_as_keynames,
+            selection,
+            procfile,
         } => {
+            let project_id = local_config
+                .resolve_project(selection.project_id, selection.stage.as_deref())?
+                .or(default_project);
             let exit_code = command::run::run(
                 client,
                 organization_id,
-                project_id,
-                uuids_as_keynames,
-                no_inherit_env,
-                shell,
-                command,
+                timeout,
+                command::run::RunCommandModel {
+                    project_id,
+                    tags: selection.tags,
+                    uuids_as_keynames: selection.uuids_as_keynames,
+                    no_inherit_env,
+                    shell,
+                    command,
+                    procfile,
+                    color: output_settings.color.is_enabled(),
+                },
             )
             .await?;
 
@@ -153,17 +404,300 @@ async fn process_commands() -> Result<()> {
             std::process::exit(exit_code);
         }
 
-        Commands::Config { .. } | Commands::Completions { .. } => {
+        Commands::Ci {
+            cmd:
+                CiCommand::Export {
+                    provider,
+                    dotenv_file,
+                    selection,
+                },
+        } => {
+            let project_id = local_config
+                .resolve_project(selection.project_id, selection.stage.as_deref())?
+                .or(default_project);
+            command::ci::export(
+                client,
+                organization_id,
+                timeout,
+                command::ci::CiExportCommandModel {
+                    provider,
+                    dotenv_file,
+                    project_id,
+                    tags: selection.tags,
+                    uuids_as_keynames: selection.uuids_as_keynames,
+                },
+            )
+            .await
+        }
+
+        Commands::Check { project_id, stage } => {
+            let project_id = local_config
+                .resolve_project(project_id, stage.as_deref())?
+                .or(default_project);
+            util::with_timeout(
+                timeout,
+                command::check::check(client, organization_id, project_id, local_config.required),
+            )
+            .await?
+        }
+
+        Commands::Diff {
+            project_id,
+            other,
+            keys_only,
+        } => {
+            let exit_code = util::with_timeout(
+                timeout,
+                command::diff::diff(
+                    client,
+                    command::diff::DiffCommandModel {
+                        project_id,
+                        other,
+                        keys_only,
+                        reveal: cli.reveal,
+                    },
+                    output_settings,
+                ),
+            )
+            .await??;
+
+            std::process::exit(exit_code);
+        }
+
+        Commands::Scan {
+            paths,
+            git_staged,
+            project_id,
+            min_length,
+        } => {
+            let exit_code = util::with_timeout(
+                timeout,
+                command::scan::scan(
+                    client,
+                    organization_id,
+                    command::scan::ScanCommandModel {
+                        paths,
+                        git_staged,
+                        project_id,
+                        min_length: min_length.try_into().unwrap_or(usize::MAX),
+                    },
+                    output_settings,
+                ),
+            )
+            .await??;
+
+            std::process::exit(exit_code);
+        }
+
+        Commands::Report {
+            project_id,
+            stale_after,
+            min_entropy,
+            fail_on,
+        } => {
+            let exit_code = util::with_timeout(
+                timeout,
+                command::report::report(
+                    client,
+                    organization_id,
+                    command::report::ReportCommandModel {
+                        project_id,
+                        stale_after,
+                        min_entropy,
+                        fail_on,
+                    },
+                    output_settings,
+                ),
+            )
+            .await??;
+
+            std::process::exit(exit_code);
+        }
+
+        Commands::Backup { out, passphrase } => {
+            util::with_timeout(
+                timeout,
+                command::backup::backup(client, organization_id, out, passphrase),
+            )
+            .await?
+        }
+
+        Commands::Restore {
+            file,
+            passphrase,
+            on_conflict,
+            dry_run,
+        } => {
+            command::backup::restore(
+                client,
+                organization_id,
+                command::backup::RestoreCommandModel {
+                    file,
+                    passphrase,
+                    on_conflict,
+                    dry_run,
+                },
+                output_settings,
+                confirmation,
+            )
+            .await
+        }
+
+        Commands::Undo { id } => {
+            command::journal::undo(
+                client,
+                organization_id,
+                journal,
+                id,
+                output_settings,
+                confirmation,
+            )
+            .await
+        }
+
+        Commands::Tui => {
+            command::tui::tui(
+                client,
+                organization_id,
+                output_settings,
+                confirmation,
+                journal,
+            )
+            .await
+        }
+
+        Commands::External(args) => {
+            let exit_code = command::plugin::run(
+                args,
+                command::plugin::PluginContext {
+                    access_token,
+                    organization_id,
+                    api_url,
+                    identity_url,
+                    profile: cli_profile,
+                    server_url: cli.server_url,
+                },
+            )?;
+
+            std::process::exit(exit_code);
+        }
+
+        Commands::Config { .. }
+        | Commands::Completions { .. }
+        | Commands::Complete { .. }
+        | Commands::State { .. }
+        | Commands::Migrate { .. }
+        | Commands::Audit { .. }
+        | Commands::Status { .. }
+        | Commands::Journal { .. }
+        | Commands::Plugins { .. } => {
             unreachable!()
         }
     }
 }
 
+fn get_client_settings(profile: &Option<Profile>) -> Result<ClientSettings> {
+    Ok(match profile {
+        Some(p) => ClientSettings {
+            identity_url: p.identity_url()?,
+            api_url: p.api_url()?,
+            ..Default::default()
+        },
+        None => ClientSettings::default(),
+    })
+}
+
+fn get_state_file(
+    profile: &Option<Profile>,
+    access_token: &AccessToken,
+    allow_insecure_state: bool,
+) -> Option<PathBuf> {
+    if get_state_opt_out(profile) {
+        return None;
+    }
+
+    match state::get_state_file(
+        profile
+            .as_ref()
+            .and_then(|p| p.state_dir.clone())
+            .map(Into::into),
+        access_token.access_token_id.to_string(),
+        allow_insecure_state,
+    ) {
+        Ok(state_file) => Some(state_file),
+        Err(e) => {
+            eprintln!("Warning: {}\nRetrieving the state file failed. Attempting to continue without using state. Please set \"state_dir\" in your config file to avoid authentication limits.", e);
+            None
+        }
+    }
+}
+
+fn load_profile(name: &str, config_file: &Option<PathBuf>) -> Result<Profile> {
+    let config = config::load_config(config_file.as_deref(), config_file.is_some())?;
+    match config.select_profile(name, true)? {
+        Some(profile) => Ok(profile),
+        None => bail!(CliError::new(
+            ErrorCategory::NotFound,
+            format!("Profile `{name}` doesn't exist")
+        )),
+    }
+}
+
+/// Logs in with the access token from the `access_token_file` or `access_token_command` of a
+/// profile, for commands that work with more than one organization at once.
+async fn login_with_profile(
+    name: &str,
+    profile: &Profile,
+    allow_insecure_state: bool,
+) -> Result<(bitwarden::Client, uuid::Uuid)> {
+    let Some(access_token) = profile.access_token()? else {
+        bail!(CliError::new(
+            ErrorCategory::Auth,
+            format!("Profile `{name}` has no `access_token_file` or `access_token_command`")
+        ));
+    };
+    let access_token_obj: AccessToken = access_token.parse()?;
+
+    // Each client loads its trusted certificates when it first connects, so the certificate of
+    // this profile is only in the environment until it has logged in
+    let cert_dir = std::env::var_os("SSL_CERT_DIR");
+    let ca_dir = apply_network_settings(profile)?;
+
+    let timeout = profile.timeout()?;
+    let selected = Some(profile.clone());
+    let client = bitwarden::Client::new(Some(get_client_settings(&selected)?));
+    let state_file = get_state_file(&selected, &access_token_obj, allow_insecure_state);
+
+    let result = util::with_timeout(
+        timeout,
+        client.auth().login_access_token(&AccessTokenLoginRequest {
+            access_token: access_token.clone(),
+            state_file,
+        }),
+    )
+    .await;
+    drop(ca_dir);
+    match cert_dir {
+        Some(cert_dir) => std::env::set_var("SSL_CERT_DIR", cert_dir),
+        None => std::env::remove_var("SSL_CERT_DIR"),
+    }
+    result??;
+
+    let Some(organization_id) = client.internal.get_access_token_organization() else {
+        bail!(CliError::new(
+            ErrorCategory::Auth,
+            format!("The access token of profile `{name}` isn't associated to an organization")
+        ));
+    };
+
+    Ok((client, organization_id))
+}
+
 fn get_config_profile(
     server_url: &Option<String>,
     profile: &Option<String>,
     config_file: &Option<PathBuf>,
-    access_token: &str,
+    access_token: Option<&str>,
 ) -> Result<Option<config::Profile>, color_eyre::Report> {
     let profile = if let Some(server_url) = server_url {
         Some(config::Profile::from_url(server_url)?)
@@ -172,10 +706,12 @@ fn get_config_profile(
 
         let profile_key = if let Some(profile) = profile {
             profile.to_owned()
-        } else {
+        } else if let Some(access_token) = access_token {
             AccessToken::from_str(access_token)?
                 .access_token_id
                 .to_string()
+        } else {
+            String::from("default")
         };
 
         let config = config::load_config(config_file.as_deref(), config_file.is_some())?;
@@ -184,6 +720,118 @@ fn get_config_profile(
     Ok(profile)
 }
 
+/// Resolves the access token, in order of precedence, from stdin, the `--access-token` flag or its
+/// environment variable, and finally the `access_token_file` or `access_token_command` of the
+/// selected profile.
+fn get_access_token(
+    access_token: Option<String>,
+    access_token_stdin: bool,
+    profile: &Option<String>,
+    config_file: &Option<PathBuf>,
+) -> Result<String> {
+    if access_token_stdin {
+        let mut buffer = String::new();
+        std::io::stdin().read_line(&mut buffer)?;
+
+        let access_token = buffer.trim();
+        if access_token.is_empty() {
+            bail!(CliError::new(
+                ErrorCategory::Auth,
+                "No access token was provided on stdin"
+            ));
+        }
+        return Ok(access_token.to_string());
+    }
+
+    if let Some(access_token) = access_token {
+        return Ok(access_token);
+    }
+
+    if let Some(socket) = std::env::var_os(agent::AGENT_SOCKET_VAR_NAME) {
+        return agent::read_token(socket.as_ref());
+    }
+
+    let config = config::load_config(config_file.as_deref(), config_file.is_some())?;
+    let profile_key = profile.as_deref().unwrap_or("default");
+    if let Some(profile) = config.select_profile(profile_key, profile.is_some())? {
+        if let Some(access_token) = profile.access_token()? {
+            return Ok(access_token);
+        }
+    }
+
+    bail!(CliError::new(ErrorCategory::Auth, "Missing access token"))
+}
+
+/// Uses the value from the profile when the flag was left at its default value.
+fn flag_or_profile_default<T: ValueEnum>(
+    matches: &ArgMatches,
+    id: &str,
+    value: T,
+    profile_value: Option<&str>,
+) -> Result<T> {
+    if matches.value_source(id) != Some(ValueSource::DefaultValue) {
+        return Ok(value);
+    }
+
+    match profile_value.map(|v| T::from_str(v, true)) {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(e)) => bail!(CliError::new(
+            ErrorCategory::Validation,
+            format!("Profile key \"{id}\": {e}")
+        )),
+        None => Ok(value),
+    }
+}
+
+/// The HTTP client reads its proxy, and on Linux its trusted certificates, from the environment,
+/// so the profile settings are applied there unless the variables are already set. The returned
+/// directory holds the extra certificate and has to be kept until the client has connected.
+fn apply_network_settings(profile: &Profile) -> Result<Option<tempfile::TempDir>> {
+    if let Some(http_proxy) = &profile.http_proxy {
+        if ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]
+            .iter()
+            .all(|var| std::env::var_os(var).is_none())
+        {
+            std::env::set_var("HTTPS_PROXY", http_proxy);
+            std::env::set_var("HTTP_PROXY", http_proxy);
+        }
+    }
+
+    let Some(ca_cert_path) = &profile.ca_cert_path else {
+        return Ok(None);
+    };
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "\"ca_cert_path\" isn't supported on this platform, remove it from the profile"
+        ));
+    }
+    if std::env::var_os("SSL_CERT_DIR").is_some() {
+        return Ok(None);
+    }
+
+    // SSL_CERT_FILE would replace the system bundle, while the certificates of SSL_CERT_DIR are
+    // added to it. Only files named like the hashes of `openssl rehash` are read from there.
+    let ca_dir = tempfile::tempdir()?;
+    std::fs::copy(ca_cert_path, ca_dir.path().join("00000000.0"))
+        .wrap_err_with(|| format!("Failed to read \"ca_cert_path\" {ca_cert_path}"))?;
+    std::env::set_var("SSL_CERT_DIR", ca_dir.path());
+
+    Ok(Some(ca_dir))
+}
+
+/// The subcommands of the invocation, such as `secret delete`.
+fn command_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        names.push(name);
+        matches = sub_matches;
+    }
+
+    names.join(" ")
+}
+
 fn get_state_opt_out(profile: &Option<Profile>) -> bool {
     if let Some(profile) = profile {
         if let Some(state_opt_out) = &profile.state_opt_out {
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use color_eyre::eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CliError, ErrorCategory};
+
+const DELIMITER: &str = "---";
+
+/// Metadata kept in a YAML front-matter block at the start of a secret's note:
+///
+/// ```text
+/// ---
+/// tags: [database, prod]
+/// owner: platform-team
+/// expires: 2025-06-30
+/// ---
+/// Free text.
+/// ```
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SecretMetadata {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) expires: Option<NaiveDate>,
+    /// Keys bws doesn't know, kept as they are when the metadata is rewritten.
+    #[serde(flatten)]
+    pub(crate) other: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl SecretMetadata {
+    fn is_empty(&self) -> bool {
+        *self == SecretMetadata::default()
+    }
+}
+
+/// Splits a note into its metadata and the free text after it. A note without front-matter has
+/// empty metadata, while front-matter that isn't valid YAML is an error, so it's never overwritten.
+pub(crate) fn parse_note(note: &str) -> Result<(SecretMetadata, &str)> {
+    let Some((first_line, rest)) = note.split_once('\n') else {
+        return Ok((SecretMetadata::default(), note));
+    };
+    if first_line.trim_end() != DELIMITER {
+        return Ok((SecretMetadata::default(), note));
+    }
+
+    let mut yaml_len = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == DELIMITER {
+            let text = &rest[yaml_len + line.len()..];
+            let metadata = match rest[..yaml_len].trim() {
+                "" => SecretMetadata::default(),
+                yaml => match serde_yaml::from_str(yaml) {
+                    Ok(metadata) => metadata,
+                    Err(e) => bail!(CliError::new(
+                        ErrorCategory::Validation,
+                        format!("The front-matter of the note is invalid: {e}")
+                    )),
+                },
+            };
+            return Ok((metadata, text));
+        }
+        yaml_len += line.len();
+    }
+
+    // Without a closing delimiter, the note just starts with a horizontal rule
+    Ok((SecretMetadata::default(), note))
+}
+
+/// Joins metadata and free text into a note, leaving out the front-matter when there's no metadata.
+pub(crate) fn format_note(metadata: &SecretMetadata, text: &str) -> Result<String> {
+    if metadata.is_empty() {
+        return Ok(text.to_string());
+    }
+
+    Ok(format!(
+        "{DELIMITER}\n{}{DELIMITER}\n{text}",
+        serde_yaml::to_string(metadata)?
+    ))
+}
+
+/// Whether the note has every one of `tags`. Notes with invalid front-matter have no tags.
+pub(crate) fn has_tags(note: &str, tags: &[String]) -> bool {
+    if tags.is_empty() {
+        return true;
+    }
+
+    match parse_note(note) {
+        Ok((metadata, _)) => tags.iter().all(|tag| metadata.tags.contains(tag)),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_front_matter() {
+        let note = "---\ntags: [db, prod]\nowner: platform\nexpires: 2025-06-30\nticket: OPS-1\n---\nRotate with care.\n";
+
+        let (metadata, text) = parse_note(note).unwrap();
+
+        assert_eq!(vec!["db", "prod"], metadata.tags);
+        assert_eq!(Some("platform".to_string()), metadata.owner);
+        assert_eq!(NaiveDate::from_ymd_opt(2025, 6, 30), metadata.expires);
+        assert!(metadata.other.contains_key("ticket"));
+        assert_eq!("Rotate with care.\n", text);
+    }
+
+    #[test]
+    fn notes_without_front_matter_are_text() {
+        for note in [
+            "",
+            "Just text",
+            "---",
+            "---\nnot closed",
+            "Text\n---\nmore\n---\n",
+        ] {
+            let (metadata, text) = parse_note(note).unwrap();
+            assert!(metadata.is_empty());
+            assert_eq!(note, text);
+        }
+    }
+
+    #[test]
+    fn invalid_front_matter_is_an_error() {
+        assert!(parse_note("---\ntags: [unclosed\n---\ntext").is_err());
+        assert!(!has_tags(
+            "---\ntags: [unclosed\n---\n",
+            &["unclosed".to_string()]
+        ));
+    }
+
+    #[test]
+    fn rewriting_preserves_text_and_unknown_keys() {
+        let note = "---\nticket: OPS-1\n---\nLine one\n\n---\nLine two";
+
+        let (mut metadata, text) = parse_note(note).unwrap();
+        metadata.tags.push("prod".to_string());
+        let rewritten = format_note(&metadata, text).unwrap();
+
+        let (mut metadata, text) = parse_note(&rewritten).unwrap();
+        assert_eq!(vec!["prod"], metadata.tags);
+        assert!(metadata.other.contains_key("ticket"));
+        assert_eq!("Line one\n\n---\nLine two", text);
+        assert!(has_tags(&rewritten, &["prod".to_string()]));
+
+        metadata.tags.clear();
+        metadata.other.clear();
+        assert_eq!(text, format_note(&metadata, text).unwrap());
+    }
+}
@@ -0,0 +1,444 @@
+//! Runs the processes of a Procfile side by side, like foreman. Their output is interleaved line by
+//! line behind the process name, and once any of them exits the others are stopped.
+
+use std::{process, time::Duration};
+
+use color_eyre::eyre::{bail, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::{mpsc, watch},
+};
+
+use crate::error::{CliError, ErrorCategory};
+
+/// How long processes get to exit after they're asked to, before they're killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ANSI colors for the process names, reused in order when there are more processes.
+const COLORS: &[u8] = &[36, 33, 32, 35, 34, 31];
+
+/// The prefix of the lines bws prints about the processes.
+const SYSTEM_NAME: &str = "system";
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct ProcfileEntry {
+    pub(crate) name: String,
+    pub(crate) command: String,
+}
+
+/// Parses `name: command` lines, skipping blank lines and `#` comments.
+pub(crate) fn parse(text: &str) -> Result<Vec<ProcfileEntry>> {
+    let mut entries: Vec<ProcfileEntry> = Vec::new();
+
+    for (number, line) in text.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((name, command)) = line.split_once(':') else {
+            bail!(invalid(number, "expected `name: command`"));
+        };
+        let (name, command) = (name.trim(), command.trim());
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            bail!(invalid(
+                number,
+                "process names may only contain letters, digits, `_` and `-`"
+            ));
+        }
+        if command.is_empty() {
+            bail!(invalid(number, "the command is empty"));
+        }
+        if entries.iter().any(|e| e.name == name) {
+            bail!(invalid(number, &format!("`{name}` is defined twice")));
+        }
+
+        entries.push(ProcfileEntry {
+            name: name.to_string(),
+            command: command.to_string(),
+        });
+    }
+
+    if entries.is_empty() {
+        bail!(CliError::new(
+            ErrorCategory::Validation,
+            "The Procfile doesn't define any processes"
+        ));
+    }
+
+    Ok(entries)
+}
+
+fn invalid(number: usize, problem: &str) -> CliError {
+    CliError::new(
+        ErrorCategory::Validation,
+        format!("Invalid Procfile line {number}: {problem}"),
+    )
+}
+
+/// Writes the lines of each process behind its name, padded to the longest name.
+#[derive(Clone)]
+struct Prefixer {
+    width: usize,
+    color: bool,
+}
+
+impl Prefixer {
+    fn print(&self, name: &str, index: Option<usize>, line: &str) {
+        let name = format!("{name:<width$}", width = self.width);
+        match (self.color, index) {
+            (true, Some(index)) => {
+                let color = COLORS[index % COLORS.len()];
+                println!("\x1b[{color}m{name} |\x1b[0m {line}");
+            }
+            (true, None) => println!("\x1b[1m{name} |\x1b[0m {line}"),
+            (false, _) => println!("{name} | {line}"),
+        }
+    }
+}
+
+/// Starts every process and waits until they've all exited, returning the exit code of the first
+/// one to exit. Interrupt, terminate and hangup signals are passed on to the processes.
+pub(crate) async fn run(
+    entries: Vec<ProcfileEntry>,
+    commands: Vec<process::Command>,
+    color: bool,
+) -> Result<i32> {
+    let prefixer = Prefixer {
+        width: entries
+            .iter()
+            .map(|e| e.name.len())
+            .chain([SYSTEM_NAME.len()])
+            .max()
+            .unwrap_or_default(),
+        color,
+    };
+
+    let (exited_tx, mut exited_rx) = mpsc::unbounded_channel();
+    let (kill_tx, kill_rx) = watch::channel(false);
+    let mut pids = Vec::new();
+    let mut output = Vec::new();
+    // Listening starts before anything is spawned, so a signal can't kill bws and leave the
+    // processes that were already started running
+    let mut signals = Signals::new()?;
+    let mut deadline = None;
+
+    for (index, (entry, command)) in entries.iter().zip(commands).enumerate() {
+        if let Some(signal) = signals.try_recv().await {
+            prefixer.print(
+                SYSTEM_NAME,
+                None,
+                &format!("received {}, stopping all processes", signal.name()),
+            );
+            signal.forward(&pids, &kill_tx);
+            deadline = Some(tokio::time::Instant::now() + STOP_TIMEOUT);
+            break;
+        }
+
+        let mut command = tokio::process::Command::from(command);
+        command
+            .stdin(process::Stdio::null())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped());
+        // Each process gets its own process group, so that a Ctrl+C in the terminal only reaches
+        // bws, which passes it on, and signals reach whatever the shell started
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                kill(&pids, &kill_tx);
+                bail!("Failed to start {}: {e}", entry.name);
+            }
+        };
+        prefixer.print(
+            SYSTEM_NAME,
+            None,
+            &format!(
+                "{} started with pid {}",
+                entry.name,
+                child.id().unwrap_or_default()
+            ),
+        );
+        pids.extend(child.id());
+
+        for stream in [
+            child
+                .stdout
+                .take()
+                .map(|s| Box::new(s) as Box<dyn AsyncRead + Send + Unpin>),
+            child
+                .stderr
+                .take()
+                .map(|s| Box::new(s) as Box<dyn AsyncRead + Send + Unpin>),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            output.push(tokio::spawn(print_lines(
+                stream,
+                entry.name.clone(),
+                index,
+                prefixer.clone(),
+            )));
+        }
+
+        let exited_tx = exited_tx.clone();
+        let mut kill_rx = kill_rx.clone();
+        tokio::spawn(async move {
+            let status = tokio::select! {
+                status = child.wait() => status,
+                _ = kill_rx.changed() => {
+                    let _ = child.start_kill();
+                    child.wait().await
+                }
+            };
+            let _ = exited_tx.send((index, status));
+        });
+    }
+    drop(exited_tx);
+
+    let mut exit_code = None;
+
+    loop {
+        tokio::select! {
+            exited = exited_rx.recv() => {
+                let Some((index, status)) = exited else {
+                    break;
+                };
+                let code = match status {
+                    Ok(status) => status.code().unwrap_or(1),
+                    Err(_) => 1,
+                };
+                prefixer.print(
+                    SYSTEM_NAME,
+                    None,
+                    &format!("{} exited with code {code}", entries[index].name),
+                );
+
+                if exit_code.is_none() {
+                    exit_code = Some(code);
+                    prefixer.print(SYSTEM_NAME, None, "stopping all processes");
+                    terminate(&pids, &kill_tx);
+                    deadline = Some(tokio::time::Instant::now() + STOP_TIMEOUT);
+                }
+            }
+            signal = signals.recv() => {
+                prefixer.print(SYSTEM_NAME, None, &format!("received {}, stopping all processes", signal.name()));
+                signal.forward(&pids, &kill_tx);
+                deadline.get_or_insert(tokio::time::Instant::now() + STOP_TIMEOUT);
+            }
+            _ = sleep_until(deadline) => {
+                prefixer.print(SYSTEM_NAME, None, "killing the processes that are still running");
+                kill(&pids, &kill_tx);
+                deadline = None;
+            }
+        }
+    }
+
+    for task in output {
+        let _ = task.await;
+    }
+
+    Ok(exit_code.unwrap_or(1))
+}
+
+async fn print_lines(
+    stream: impl AsyncRead + Unpin,
+    name: String,
+    index: usize,
+    prefixer: Prefixer,
+) {
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let text = String::from_utf8_lossy(&line);
+                prefixer.print(&name, Some(index), text.trim_end_matches(['\n', '\r']));
+            }
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Asks every process to exit. Only Unix can ask, so elsewhere they're killed.
+#[cfg_attr(unix, allow(unused_variables))]
+fn terminate(pids: &[u32], kill_tx: &watch::Sender<bool>) {
+    #[cfg(unix)]
+    signal_all(pids, nix::sys::signal::Signal::SIGTERM);
+    #[cfg(not(unix))]
+    kill(pids, kill_tx);
+}
+
+/// Kills every process that's still running, and on Unix whatever it started too.
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn kill(pids: &[u32], kill_tx: &watch::Sender<bool>) {
+    #[cfg(unix)]
+    signal_all(pids, nix::sys::signal::Signal::SIGKILL);
+    let _ = kill_tx.send(true);
+}
+
+#[cfg(unix)]
+fn signal_all(pids: &[u32], signal: nix::sys::signal::Signal) {
+    use nix::{sys::signal::killpg, unistd::Pid};
+
+    for pid in pids {
+        // The process group is gone once everything in it has exited
+        let _ = killpg(Pid::from_raw(*pid as i32), signal);
+    }
+}
+
+#[cfg(unix)]
+struct Signal(nix::sys::signal::Signal);
+
+#[cfg(unix)]
+impl Signal {
+    fn name(&self) -> &'static str {
+        self.0.as_str()
+    }
+
+    fn forward(&self, pids: &[u32], _kill_tx: &watch::Sender<bool>) {
+        signal_all(pids, self.0);
+    }
+}
+
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    hangup: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Signals {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    async fn recv(&mut self) -> Signal {
+        use nix::sys::signal::Signal::{SIGHUP, SIGINT, SIGTERM};
+
+        tokio::select! {
+            _ = self.interrupt.recv() => Signal(SIGINT),
+            _ = self.terminate.recv() => Signal(SIGTERM),
+            _ = self.hangup.recv() => Signal(SIGHUP),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Signal;
+
+#[cfg(not(unix))]
+impl Signal {
+    fn name(&self) -> &'static str {
+        "Ctrl+C"
+    }
+
+    fn forward(&self, pids: &[u32], kill_tx: &watch::Sender<bool>) {
+        kill(pids, kill_tx);
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> Result<Self> {
+        Ok(Signals)
+    }
+
+    async fn recv(&mut self) -> Signal {
+        let _ = tokio::signal::ctrl_c().await;
+        Signal
+    }
+}
+
+impl Signals {
+    /// Returns a signal that has already arrived, without waiting for one.
+    async fn try_recv(&mut self) -> Option<Signal> {
+        tokio::select! {
+            biased;
+            signal = self.recv() => Some(signal),
+            _ = std::future::ready(()) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries() {
+        let entries = parse(
+            "# Processes for local development\n\
+             web: ./bin/server --port=$PORT\n\
+             \n\
+             worker_1:bundle exec sidekiq\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ProcfileEntry {
+                    name: "web".to_string(),
+                    command: "./bin/server --port=$PORT".to_string()
+                },
+                ProcfileEntry {
+                    name: "worker_1".to_string(),
+                    command: "bundle exec sidekiq".to_string()
+                },
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        assert!(parse("").is_err());
+        assert!(parse("web ./bin/server").is_err());
+        assert!(parse("web server: ./bin/server").is_err());
+        assert!(parse("web:").is_err());
+        assert!(parse("web: a\nweb: b").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn stops_the_others_when_one_exits() {
+        let entries = parse("web: sleep 30\nmigrate: exit 3").unwrap();
+        let commands = entries
+            .iter()
+            .map(|e| {
+                let mut command = process::Command::new("sh");
+                command.arg("-c").arg(&e.command);
+                command
+            })
+            .collect();
+
+        let started = std::time::Instant::now();
+        assert_eq!(3, run(entries, commands, false).await.unwrap());
+        assert!(started.elapsed() < STOP_TIMEOUT);
+    }
+}
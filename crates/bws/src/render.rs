@@ -1,21 +1,53 @@
 use bitwarden::secrets_manager::{projects::ProjectResponse, secrets::SecretResponse};
 use bitwarden_cli::Color;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use comfy_table::Table;
 use serde::Serialize;
 
-use crate::{cli::Output, util::is_valid_posix_name};
+use crate::{
+    cli::Output,
+    command::{
+        backup::{RestoreAction, RestoreItem, RestoreKind},
+        copy::CopyChange,
+        diff::DiffEntry,
+        metadata::ExpiringSecret,
+        plugin::PluginInfo,
+        rename::SecretRename,
+        report::{severity_name, ReportFinding},
+        scan::ScanFinding,
+        status::StatusResponse,
+    },
+    config::{ConfigEntry, ProfileSummary},
+    journal::JournalSummary,
+    state::StateFileInfo,
+    util::is_valid_posix_name,
+};
 
 const ASCII_HEADER_ONLY: &str = "     --            ";
 
+const MASKED_VALUE: &str = "********";
+
 pub(crate) struct OutputSettings {
     pub(crate) output: Output,
     pub(crate) color: Color,
+    pub(crate) reveal: bool,
 }
 
 impl OutputSettings {
     pub(crate) fn new(output: Output, color: Color) -> Self {
-        OutputSettings { output, color }
+        OutputSettings {
+            output,
+            color,
+            reveal: true,
+        }
+    }
+
+    /// Hides a secret value unless values should be revealed.
+    pub(crate) fn mask(&self, value: String) -> String {
+        match self.reveal {
+            true => value,
+            false => MASKED_VALUE.to_string(),
+        }
     }
 }
 
@@ -152,3 +184,244 @@ impl TableSerialize<4> for SecretResponse {
         ]]
     }
 }
+
+impl TableSerialize<3> for StateFileInfo {
+    fn get_headers() -> [&'static str; 3] {
+        ["Access Token ID", "Path", "Revision Date"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 3]> {
+        vec![[
+            self.access_token_id.clone(),
+            self.path.display().to_string(),
+            format_date(&self.revision_date),
+        ]]
+    }
+}
+
+impl TableSerialize<3> for ConfigEntry {
+    fn get_headers() -> [&'static str; 3] {
+        ["Key", "Value", "Source"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 3]> {
+        vec![[
+            self.key.clone(),
+            self.value.clone(),
+            self.source.to_string(),
+        ]]
+    }
+}
+
+impl TableSerialize<3> for ProfileSummary {
+    fn get_headers() -> [&'static str; 3] {
+        ["Name", "API URL", "Identity URL"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 3]> {
+        vec![[
+            self.name.clone(),
+            self.api_url.clone().unwrap_or_default(),
+            self.identity_url.clone().unwrap_or_default(),
+        ]]
+    }
+}
+
+impl TableSerialize<2> for StatusResponse {
+    fn get_headers() -> [&'static str; 2] {
+        ["Key", "Value"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 2]> {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+        let mut values = vec![
+            [
+                "Access Token ID".to_string(),
+                self.access_token_id.to_string(),
+            ],
+            [
+                "Organization ID".to_string(),
+                optional(self.organization_id.map(|id| id.to_string())),
+            ],
+            ["API URL".to_string(), self.api_url.clone()],
+            ["Identity URL".to_string(), self.identity_url.clone()],
+            [
+                "Profile".to_string(),
+                format!("{} ({})", self.profile, self.profile_source),
+            ],
+            [
+                "State File".to_string(),
+                optional(self.state_file.as_ref().map(|f| f.display().to_string())),
+            ],
+            [
+                "State File Age".to_string(),
+                optional(
+                    self.state_file_revision_date
+                        .map(|d| format_age(Utc::now() - d)),
+                ),
+            ],
+        ];
+
+        if let Some(check) = &self.check {
+            values.push([
+                "Login Check".to_string(),
+                match check.success {
+                    true => "success".to_string(),
+                    false => "failed".to_string(),
+                },
+            ]);
+            values.push(["Latency".to_string(), format!("{} ms", check.latency_ms)]);
+            if let Some(category) = check.error_category {
+                values.push(["Failure Category".to_string(), category.to_string()]);
+            }
+            if let Some(error) = &check.error {
+                values.push(["Error".to_string(), error.clone()]);
+            }
+        }
+
+        values
+    }
+}
+
+fn format_age(age: TimeDelta) -> String {
+    match age.num_seconds() {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+    }
+}
+
+impl TableSerialize<3> for SecretRename {
+    fn get_headers() -> [&'static str; 3] {
+        ["ID", "Key", "New Key"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 3]> {
+        vec![[self.id.to_string(), self.key.clone(), self.new_key.clone()]]
+    }
+}
+
+impl TableSerialize<2> for CopyChange {
+    fn get_headers() -> [&'static str; 2] {
+        ["Key", "Action"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 2]> {
+        vec![[self.key.clone(), self.action.to_string()]]
+    }
+}
+
+impl TableSerialize<4> for DiffEntry {
+    fn get_headers() -> [&'static str; 4] {
+        ["Key", "Status", "Left", "Right"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 4]> {
+        vec![[
+            self.key.clone(),
+            self.status.to_string(),
+            self.left.clone().unwrap_or_default(),
+            self.right.clone().unwrap_or_default(),
+        ]]
+    }
+}
+
+impl TableSerialize<5> for RestoreItem {
+    fn get_headers() -> [&'static str; 5] {
+        ["Kind", "Name", "Action", "Old ID", "New ID"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 5]> {
+        let kind = match self.kind {
+            RestoreKind::Project => "project",
+            RestoreKind::Secret => "secret",
+        };
+        let action = match self.action {
+            RestoreAction::Create => "create",
+            RestoreAction::Update => "update",
+            RestoreAction::Skip => "skip",
+        };
+
+        vec![[
+            kind.to_string(),
+            self.name.clone(),
+            action.to_string(),
+            self.old_id.to_string(),
+            self.new_id.map(|id| id.to_string()).unwrap_or_default(),
+        ]]
+    }
+}
+
+impl TableSerialize<4> for JournalSummary {
+    fn get_headers() -> [&'static str; 4] {
+        ["ID", "Deleted", "Projects", "Secrets"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 4]> {
+        vec![[
+            self.id.to_string(),
+            format_date(&self.deleted),
+            self.projects.join(", "),
+            self.secrets.join(", "),
+        ]]
+    }
+}
+
+impl TableSerialize<4> for ScanFinding {
+    fn get_headers() -> [&'static str; 4] {
+        ["File", "Line", "Key", "Secret ID"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 4]> {
+        vec![[
+            self.file.clone(),
+            self.line.to_string(),
+            self.key.clone(),
+            self.secret_id.to_string(),
+        ]]
+    }
+}
+
+impl TableSerialize<5> for ReportFinding {
+    fn get_headers() -> [&'static str; 5] {
+        ["Severity", "Check", "Key", "ID", "Detail"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 5]> {
+        vec![[
+            severity_name(self.severity),
+            self.check.to_string(),
+            self.key.clone(),
+            self.secret_id.to_string(),
+            self.detail.clone(),
+        ]]
+    }
+}
+
+impl TableSerialize<5> for ExpiringSecret {
+    fn get_headers() -> [&'static str; 5] {
+        ["ID", "Key", "Owner", "Expires", "Days Left"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 5]> {
+        vec![[
+            self.id.to_string(),
+            self.key.clone(),
+            self.owner.clone().unwrap_or_default(),
+            self.expires.to_string(),
+            self.days_left.to_string(),
+        ]]
+    }
+}
+
+impl TableSerialize<2> for PluginInfo {
+    fn get_headers() -> [&'static str; 2] {
+        ["Name", "Path"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 2]> {
+        vec![[self.name.clone(), self.path.display().to_string()]]
+    }
+}
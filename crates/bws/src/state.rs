@@ -1,32 +1,153 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{bail, Result};
 use directories::BaseDirs;
+use serde::Serialize;
 
-use crate::DEFAULT_CONFIG_DIRECTORY;
+use crate::{
+    error::{CliError, ErrorCategory},
+    util::{create_private_dir, create_private_file},
+    DEFAULT_CONFIG_DIRECTORY,
+};
 
 pub(crate) const DEFAULT_STATE_DIRECTORY: &str = "state";
 
-pub(crate) fn get_state_file(
-    state_dir: Option<PathBuf>,
-    access_token_id: String,
-) -> Result<PathBuf> {
-    let mut state_dir = match state_dir {
-        Some(state_dir) => state_dir,
+/// Information about a single state file, as shown by `bws state list`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StateFileInfo {
+    pub(crate) access_token_id: String,
+    pub(crate) path: PathBuf,
+    pub(crate) revision_date: DateTime<Utc>,
+}
+
+pub(crate) fn get_state_dir(state_dir: Option<PathBuf>) -> Result<PathBuf> {
+    match state_dir {
+        Some(state_dir) => Ok(state_dir),
         None => {
             if let Some(base_dirs) = BaseDirs::new() {
-                base_dirs
+                Ok(base_dirs
                     .home_dir()
                     .join(DEFAULT_CONFIG_DIRECTORY)
-                    .join(DEFAULT_STATE_DIRECTORY)
+                    .join(DEFAULT_STATE_DIRECTORY))
             } else {
                 bail!("A valid home directory doesn't exist");
             }
         }
-    };
+    }
+}
+
+/// Returns the state file for the given access token, creating the state directory and an empty
+/// state file with owner-only permissions if they don't exist yet.
+///
+/// The contents of the state file are encrypted by the SDK with the access token's encryption key.
+/// Since that only protects the session while the access token itself stays secret, state that is
+/// readable by other users is refused unless `allow_insecure` is set.
+pub(crate) fn get_state_file(
+    state_dir: Option<PathBuf>,
+    access_token_id: String,
+    allow_insecure: bool,
+) -> Result<PathBuf> {
+    let mut state_dir = get_state_dir(state_dir)?;
+
+    if state_dir.exists() {
+        check_permissions(&state_dir, allow_insecure)?;
+    } else {
+        create_private_dir(&state_dir)?;
+    }
 
-    std::fs::create_dir_all(&state_dir)?;
     state_dir.push(access_token_id);
 
+    if state_dir.exists() {
+        check_permissions(&state_dir, allow_insecure)?;
+    } else {
+        create_private_file(&state_dir)?;
+    }
+
     Ok(state_dir)
 }
+
+pub(crate) fn list_state_files(state_dir: &Path) -> Result<Vec<StateFileInfo>> {
+    if !state_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut state_files = Vec::new();
+    for entry in std::fs::read_dir(state_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        state_files.push(StateFileInfo {
+            access_token_id: entry.file_name().to_string_lossy().to_string(),
+            path: entry.path(),
+            revision_date: metadata.modified()?.into(),
+        });
+    }
+    state_files.sort_by_key(|s| std::cmp::Reverse(s.revision_date));
+
+    Ok(state_files)
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path, allow_insecure: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 && !allow_insecure {
+        bail!(CliError::new(
+            ErrorCategory::Permission,
+            format!(
+                "{} is accessible by other users (mode {:o}). Restrict its permissions or pass --allow-insecure-state to use it anyway.",
+                path.display(),
+                mode & 0o777
+            )
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path, _allow_insecure: bool) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn state_file_created_with_private_permissions() {
+        let dir = tempdir().unwrap();
+        let state_dir = dir.path().join("state");
+
+        let state_file = get_state_file(Some(state_dir.clone()), "id".to_string(), false).unwrap();
+
+        assert_eq!(0o700, mode(&state_dir));
+        assert_eq!(0o600, mode(&state_file));
+    }
+
+    #[test]
+    fn world_readable_state_is_refused() {
+        let dir = tempdir().unwrap();
+        let state_dir = dir.path().join("state");
+        std::fs::create_dir(&state_dir).unwrap();
+        std::fs::set_permissions(&state_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(get_state_file(Some(state_dir.clone()), "id".to_string(), false).is_err());
+        assert!(get_state_file(Some(state_dir), "id".to_string(), true).is_ok());
+    }
+}
@@ -0,0 +1,19 @@
+//! Factories shared by the unit tests.
+
+use bitwarden::secrets_manager::secrets::SecretResponse;
+use chrono::Utc;
+use uuid::Uuid;
+
+/// A secret with a random ID, created and revised now.
+pub(crate) fn secret(key: &str, value: &str, project_id: Option<Uuid>) -> SecretResponse {
+    SecretResponse {
+        id: Uuid::new_v4(),
+        organization_id: Uuid::nil(),
+        project_id,
+        key: key.to_string(),
+        value: value.to_string(),
+        note: String::new(),
+        creation_date: Utc::now(),
+        revision_date: Utc::now(),
+    }
+}
@@ -0,0 +1,534 @@
+//! The state of `bws tui` and how it reacts to keys. Drawing is in [`ui`], and the API calls are
+//! made by the event loop in `command::tui`, so the state can be tested without a terminal.
+
+use bitwarden::secrets_manager::{projects::ProjectResponse, secrets::SecretResponse};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use uuid::Uuid;
+
+pub(crate) mod ui;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Focus {
+    Projects,
+    Secrets,
+}
+
+/// An entry of the project tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ProjectNode {
+    All,
+    Project(Uuid),
+    /// The secrets that aren't in any project.
+    Unassigned,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Mode {
+    Browse,
+    /// Typing the fuzzy filter of the secret list.
+    Filter,
+    Form(SecretForm),
+    ConfirmDelete(Uuid),
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FormField {
+    Key,
+    Value,
+    Note,
+}
+
+/// The fields of a secret being created or edited.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SecretForm {
+    /// The secret being edited, or `None` when creating one.
+    pub(crate) id: Option<Uuid>,
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) note: String,
+    pub(crate) field: FormField,
+}
+
+impl SecretForm {
+    fn field_mut(&mut self) -> &mut String {
+        match self.field {
+            FormField::Key => &mut self.key,
+            FormField::Value => &mut self.value,
+            FormField::Note => &mut self.note,
+        }
+    }
+
+    fn next_field(&mut self) {
+        self.field = match self.field {
+            FormField::Key => FormField::Value,
+            FormField::Value => FormField::Note,
+            FormField::Note => FormField::Key,
+        };
+    }
+
+    fn previous_field(&mut self) {
+        self.field = match self.field {
+            FormField::Key => FormField::Note,
+            FormField::Value => FormField::Key,
+            FormField::Note => FormField::Value,
+        };
+    }
+}
+
+/// What the event loop has to do after a key press.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Action {
+    None,
+    Quit,
+    Reload,
+    Save(SecretForm),
+    Delete(Uuid),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Status {
+    Info(String),
+    Error(String),
+}
+
+pub(crate) struct App {
+    pub(crate) projects: Vec<ProjectResponse>,
+    pub(crate) secrets: Vec<SecretResponse>,
+    pub(crate) focus: Focus,
+    pub(crate) mode: Mode,
+    pub(crate) project_index: usize,
+    pub(crate) secret_index: usize,
+    pub(crate) filter: String,
+    pub(crate) reveal: bool,
+    /// Whether values may be revealed, from the `reveal` setting of the profile.
+    pub(crate) can_reveal: bool,
+    /// Protected profiles can only be browsed, as modifying them requires typing the profile name.
+    pub(crate) read_only: bool,
+    pub(crate) status: Option<Status>,
+}
+
+impl App {
+    pub(crate) fn new(can_reveal: bool, read_only: bool) -> App {
+        App {
+            projects: Vec::new(),
+            secrets: Vec::new(),
+            focus: Focus::Secrets,
+            mode: Mode::Browse,
+            project_index: 0,
+            secret_index: 0,
+            filter: String::new(),
+            reveal: false,
+            can_reveal,
+            read_only,
+            status: None,
+        }
+    }
+
+    pub(crate) fn set_data(
+        &mut self,
+        projects: Vec<ProjectResponse>,
+        secrets: Vec<SecretResponse>,
+    ) {
+        self.projects = projects;
+        self.projects.sort_by(|a, b| a.name.cmp(&b.name));
+        self.secrets = secrets;
+        self.secrets.sort_by(|a, b| a.key.cmp(&b.key));
+        self.clamp_selection();
+    }
+
+    /// Adds a created secret, or replaces an edited one.
+    pub(crate) fn upsert_secret(&mut self, secret: SecretResponse) {
+        self.secrets.retain(|s| s.id != secret.id);
+        self.secrets.push(secret);
+        self.secrets.sort_by(|a, b| a.key.cmp(&b.key));
+        self.clamp_selection();
+    }
+
+    pub(crate) fn remove_secret(&mut self, id: Uuid) {
+        self.secrets.retain(|s| s.id != id);
+        self.clamp_selection();
+    }
+
+    pub(crate) fn nodes(&self) -> Vec<ProjectNode> {
+        let mut nodes = vec![ProjectNode::All];
+        nodes.extend(self.projects.iter().map(|p| ProjectNode::Project(p.id)));
+        if self.secrets.iter().any(|s| s.project_id.is_none()) {
+            nodes.push(ProjectNode::Unassigned);
+        }
+        nodes
+    }
+
+    pub(crate) fn selected_node(&self) -> ProjectNode {
+        self.nodes()
+            .get(self.project_index)
+            .copied()
+            .unwrap_or(ProjectNode::All)
+    }
+
+    pub(crate) fn node_label(&self, node: ProjectNode) -> String {
+        let (name, count) = match node {
+            ProjectNode::All => ("All secrets", self.secrets.len()),
+            ProjectNode::Project(id) => (
+                self.project_name(id).unwrap_or_default(),
+                self.secrets
+                    .iter()
+                    .filter(|s| s.project_id == Some(id))
+                    .count(),
+            ),
+            ProjectNode::Unassigned => (
+                "No project",
+                self.secrets
+                    .iter()
+                    .filter(|s| s.project_id.is_none())
+                    .count(),
+            ),
+        };
+        format!("{name} ({count})")
+    }
+
+    pub(crate) fn project_name(&self, id: Uuid) -> Option<&str> {
+        self.projects
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| p.name.as_str())
+    }
+
+    /// The secrets of the selected project matching the filter, best matches first.
+    pub(crate) fn visible_secrets(&self) -> Vec<&SecretResponse> {
+        let node = self.selected_node();
+        let in_node = self.secrets.iter().filter(|s| match node {
+            ProjectNode::All => true,
+            ProjectNode::Project(id) => s.project_id == Some(id),
+            ProjectNode::Unassigned => s.project_id.is_none(),
+        });
+
+        if self.filter.is_empty() {
+            return in_node.collect();
+        }
+
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, &SecretResponse)> = in_node
+            .filter_map(|s| {
+                matcher
+                    .fuzzy_match(&s.key, &self.filter)
+                    .map(|score| (score, s))
+            })
+            .collect();
+        // The sort is stable, so equal scores stay sorted by key
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, s)| s).collect()
+    }
+
+    pub(crate) fn selected_secret(&self) -> Option<&SecretResponse> {
+        self.visible_secrets().get(self.secret_index).copied()
+    }
+
+    fn clamp_selection(&mut self) {
+        self.project_index = self.project_index.min(self.nodes().len() - 1);
+        self.secret_index = self
+            .secret_index
+            .min(self.visible_secrets().len().saturating_sub(1));
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let len = match self.focus {
+            Focus::Projects => self.nodes().len(),
+            Focus::Secrets => self.visible_secrets().len(),
+        };
+        let index = match self.focus {
+            Focus::Projects => &mut self.project_index,
+            Focus::Secrets => &mut self.secret_index,
+        };
+        *index = match down {
+            true => (*index + 1).min(len.saturating_sub(1)),
+            false => index.saturating_sub(1),
+        };
+
+        if self.focus == Focus::Projects {
+            self.secret_index = 0;
+        }
+    }
+
+    fn error(&mut self, message: &str) {
+        self.status = Some(Status::Error(message.to_string()));
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => self.handle_browse_key(key),
+            Mode::Filter => self.handle_filter_key(key),
+            Mode::Form(form) => self.handle_form_key(form, key),
+            Mode::ConfirmDelete(id) => {
+                if key.code == KeyCode::Char('y') {
+                    return Action::Delete(id);
+                }
+                self.status = Some(Status::Info("Nothing was deleted".to_string()));
+                Action::None
+            }
+            Mode::Help => Action::None,
+        }
+    }
+
+    fn handle_browse_key(&mut self, key: KeyEvent) -> Action {
+        self.status = None;
+
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Char('r') => return Action::Reload,
+            KeyCode::Char('?') => self.mode = Mode::Help,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Projects => Focus::Secrets,
+                    Focus::Secrets => Focus::Projects,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Projects,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::Secrets,
+            KeyCode::Enter if self.focus == Focus::Projects => self.focus = Focus::Secrets,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Char('/') => {
+                self.focus = Focus::Secrets;
+                self.mode = Mode::Filter;
+            }
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.clamp_selection();
+            }
+            KeyCode::Char('v') if self.can_reveal => self.reveal = !self.reveal,
+            KeyCode::Char('v') => {
+                self.error("Values are hidden by the `reveal` setting of the profile. Run with --reveal to show them.")
+            }
+            KeyCode::Char('n') | KeyCode::Char('e') | KeyCode::Enter | KeyCode::Char('d')
+                if self.read_only =>
+            {
+                self.error("The profile is protected, so secrets can only be browsed here");
+            }
+            KeyCode::Char('n') => {
+                let project_id = match self.selected_node() {
+                    ProjectNode::Project(id) => Some(id),
+                    _ => self.selected_secret().and_then(|s| s.project_id),
+                };
+                match project_id {
+                    Some(project_id) => {
+                        self.mode = Mode::Form(SecretForm {
+                            id: None,
+                            project_id: Some(project_id),
+                            key: String::new(),
+                            value: String::new(),
+                            note: String::new(),
+                            field: FormField::Key,
+                        })
+                    }
+                    None => self.error("Select a project to create the secret in"),
+                }
+            }
+            KeyCode::Char('e') | KeyCode::Enter => match self.selected_secret() {
+                Some(secret) => {
+                    self.mode = Mode::Form(SecretForm {
+                        id: Some(secret.id),
+                        project_id: secret.project_id,
+                        key: secret.key.clone(),
+                        value: secret.value.clone(),
+                        note: secret.note.clone(),
+                        field: FormField::Value,
+                    })
+                }
+                None => self.error("No secret is selected"),
+            },
+            KeyCode::Char('d') | KeyCode::Delete => match self.selected_secret() {
+                Some(secret) => self.mode = Mode::ConfirmDelete(secret.id),
+                None => self.error("No secret is selected"),
+            },
+            _ => (),
+        }
+
+        Action::None
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Enter => return Action::None,
+            KeyCode::Up => self.move_selection(false),
+            KeyCode::Down => self.move_selection(true),
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.secret_index = 0;
+            }
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.secret_index = 0;
+            }
+            _ => (),
+        }
+
+        if key.code != KeyCode::Esc {
+            self.mode = Mode::Filter;
+        }
+        self.clamp_selection();
+        Action::None
+    }
+
+    fn handle_form_key(&mut self, mut form: SecretForm, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Esc => return Action::None,
+            KeyCode::Char('s') if ctrl => {
+                if form.key.trim().is_empty() {
+                    self.error("The key can't be empty");
+                } else {
+                    return Action::Save(form);
+                }
+            }
+            KeyCode::Tab | KeyCode::Down => form.next_field(),
+            KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+            KeyCode::Enter if form.field == FormField::Note => form.note.push('\n'),
+            KeyCode::Enter => form.next_field(),
+            KeyCode::Backspace => {
+                form.field_mut().pop();
+            }
+            KeyCode::Char(c) if !ctrl => form.field_mut().push(c),
+            _ => (),
+        }
+
+        self.mode = Mode::Form(form);
+        Action::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::test_util::secret;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+    }
+
+    fn app(read_only: bool) -> (App, Uuid) {
+        let project_id = Uuid::new_v4();
+        let mut app = App::new(true, read_only);
+        app.set_data(
+            vec![ProjectResponse {
+                id: project_id,
+                organization_id: Uuid::nil(),
+                name: "backend".to_string(),
+                creation_date: Utc::now(),
+                revision_date: Utc::now(),
+            }],
+            vec![
+                secret("DATABASE_URL", "DATABASE_URL-value", Some(project_id)),
+                secret("API_KEY", "API_KEY-value", Some(project_id)),
+                secret("LOOSE", "LOOSE-value", None),
+            ],
+        );
+        (app, project_id)
+    }
+
+    #[test]
+    fn filters_by_project_and_fuzzy_key() {
+        let (mut app, project_id) = app(false);
+        assert_eq!(
+            vec![
+                ProjectNode::All,
+                ProjectNode::Project(project_id),
+                ProjectNode::Unassigned
+            ],
+            app.nodes()
+        );
+        assert_eq!(3, app.visible_secrets().len());
+
+        app.focus = Focus::Projects;
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!("backend (2)", app.node_label(app.selected_node()));
+        assert_eq!(2, app.visible_secrets().len());
+
+        app.handle_key(key(KeyCode::Char('/')));
+        type_text(&mut app, "dburl");
+        let keys: Vec<&str> = app
+            .visible_secrets()
+            .iter()
+            .map(|s| s.key.as_str())
+            .collect();
+        assert_eq!(vec!["DATABASE_URL"], keys);
+
+        app.handle_key(key(KeyCode::Esc));
+        assert_eq!(Mode::Browse, app.mode);
+        assert_eq!(2, app.visible_secrets().len());
+    }
+
+    #[test]
+    fn edits_and_saves_the_selected_secret() {
+        let (mut app, project_id) = app(false);
+
+        app.handle_key(key(KeyCode::Char('e')));
+        type_text(&mut app, "2");
+        let action = app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+
+        let Action::Save(form) = action else {
+            panic!("expected a save, got {action:?}");
+        };
+        assert_eq!("API_KEY", form.key);
+        assert_eq!("API_KEY-value2", form.value);
+        assert_eq!(Some(project_id), form.project_id);
+        assert_eq!(Mode::Browse, app.mode);
+    }
+
+    #[test]
+    fn delete_needs_confirmation() {
+        let (mut app, _) = app(false);
+        let id = app.selected_secret().map(|s| s.id).unwrap();
+
+        app.handle_key(key(KeyCode::Char('d')));
+        assert_eq!(Action::None, app.handle_key(key(KeyCode::Char('n'))));
+
+        app.handle_key(key(KeyCode::Char('d')));
+        assert_eq!(Action::Delete(id), app.handle_key(key(KeyCode::Char('y'))));
+    }
+
+    #[test]
+    fn draws_every_mode() {
+        let (mut app, _) = app(false);
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+
+        for code in [KeyCode::Char('?'), KeyCode::Char('d'), KeyCode::Char('e')] {
+            app.mode = Mode::Browse;
+            app.handle_key(key(code));
+            terminal.draw(|frame| ui::draw(frame, &app)).unwrap();
+        }
+
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("Edit secret"));
+        assert!(screen.contains("backend (2)"));
+        assert!(!screen.contains("API_KEY-value"));
+    }
+
+    #[test]
+    fn protected_profiles_are_read_only() {
+        let (mut app, _) = app(true);
+
+        for code in [KeyCode::Char('n'), KeyCode::Char('e'), KeyCode::Char('d')] {
+            assert_eq!(Action::None, app.handle_key(key(code)));
+            assert_eq!(Mode::Browse, app.mode);
+            assert!(matches!(app.status, Some(Status::Error(_))));
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use super::{App, Focus, FormField, Mode, SecretForm, Status};
+
+const MASKED_VALUE: &str = "********";
+
+const HELP: &[(&str, &str)] = &[
+    ("Tab, ←/→, h/l", "Switch between projects and secrets"),
+    ("↑/↓, k/j", "Move the selection"),
+    ("/", "Filter the secrets by key, Esc clears the filter"),
+    ("v", "Show or hide values"),
+    ("n", "Create a secret in the selected project"),
+    ("e, Enter", "Edit the selected secret"),
+    ("d, Delete", "Delete the selected secret"),
+    ("r", "Reload projects and secrets"),
+    ("Tab, ↑/↓", "In a form, move between fields"),
+    ("Ctrl+S", "In a form, save. Esc cancels"),
+    ("q, Ctrl+C", "Quit"),
+];
+
+pub(crate) fn draw(frame: &mut Frame, app: &App) {
+    let [main, status] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+    let [projects, secrets] =
+        Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(main);
+    let [list, detail] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(9)]).areas(secrets);
+
+    draw_projects(frame, app, projects);
+    draw_secrets(frame, app, list);
+    draw_detail(frame, app, detail);
+    draw_status(frame, app, status);
+
+    match &app.mode {
+        Mode::Form(form) => draw_form(frame, form, app.reveal),
+        Mode::ConfirmDelete(_) => {
+            let key = app.selected_secret().map(|s| s.key.as_str());
+            let area = popup(frame.area(), 50, 3);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(format!("Delete {}? [y/N]", key.unwrap_or_default()))
+                    .block(Block::bordered().title(" Delete ").red()),
+                area,
+            );
+        }
+        Mode::Help => draw_help(frame),
+        Mode::Browse | Mode::Filter => (),
+    }
+}
+
+fn pane(title: String, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(title);
+    match focused {
+        true => block.border_style(Style::new().fg(Color::Cyan)),
+        false => block,
+    }
+}
+
+fn highlight(focused: bool) -> Style {
+    match focused {
+        true => Style::new().add_modifier(Modifier::REVERSED),
+        false => Style::new().add_modifier(Modifier::BOLD),
+    }
+}
+
+fn draw_projects(frame: &mut Frame, app: &App, area: Rect) {
+    let focused = app.focus == Focus::Projects;
+    let nodes = app.nodes();
+    let items: Vec<ListItem> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            // Projects are shown under the root that lists every secret
+            let indent = if index == 0 { "" } else { "  " };
+            ListItem::new(format!("{indent}{}", app.node_label(*node)))
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(Some(app.project_index));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(pane(" Projects ".to_string(), focused))
+            .highlight_style(highlight(focused)),
+        area,
+        &mut state,
+    );
+}
+
+fn draw_secrets(frame: &mut Frame, app: &App, area: Rect) {
+    let focused = app.focus == Focus::Secrets;
+    let secrets = app.visible_secrets();
+    let items: Vec<ListItem> = secrets
+        .iter()
+        .map(|s| ListItem::new(s.key.clone()))
+        .collect();
+
+    let title = match (&app.mode, app.filter.is_empty()) {
+        (Mode::Filter, _) => format!(" Secrets /{}█ ", app.filter),
+        (_, false) => format!(" Secrets /{} ", app.filter),
+        (_, true) => " Secrets ".to_string(),
+    };
+
+    let mut state = ListState::default().with_selected(match secrets.is_empty() {
+        true => None,
+        false => Some(app.secret_index),
+    });
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(pane(title, focused))
+            .highlight_style(highlight(focused)),
+        area,
+        &mut state,
+    );
+}
+
+fn draw_detail(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::bordered().title(" Details ");
+    let Some(secret) = app.selected_secret() else {
+        frame.render_widget(Paragraph::new("No secret is selected").block(block), area);
+        return;
+    };
+
+    let value = match app.reveal {
+        true => secret.value.as_str(),
+        false => MASKED_VALUE,
+    };
+    let project = match secret.project_id {
+        Some(id) => format!("{} ({id})", app.project_name(id).unwrap_or_default()),
+        None => "None".to_string(),
+    };
+
+    let field = |name: &'static str, value: String| {
+        Line::from(vec![
+            Span::from(format!("{name:<9}")).bold(),
+            Span::from(value),
+        ])
+    };
+    let mut lines = vec![
+        field("Key", secret.key.clone()),
+        field("ID", secret.id.to_string()),
+        field("Project", project),
+        field("Value", value.to_string()),
+        field("Revised", secret.revision_date.to_rfc3339()),
+    ];
+    lines.extend(
+        secret
+            .note
+            .lines()
+            .map(|l| Line::from(l.to_string()).italic()),
+    );
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+fn draw_status(frame: &mut Frame, app: &App, area: Rect) {
+    let line = match &app.status {
+        Some(Status::Error(message)) => Line::from(message.as_str()).red(),
+        Some(Status::Info(message)) => Line::from(message.as_str()),
+        None => Line::from("? help  / filter  v reveal  n new  e edit  d delete  r reload  q quit")
+            .dark_gray(),
+    };
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+fn draw_form(frame: &mut Frame, form: &SecretForm, reveal: bool) {
+    let title = match form.id {
+        Some(_) => " Edit secret ",
+        None => " New secret ",
+    };
+    let area = popup(frame.area(), 70, 14);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Block::bordered()
+            .title(title)
+            .title_bottom(" Ctrl+S save · Esc cancel "),
+        area,
+    );
+
+    let [key, value, note] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Fill(1),
+    ])
+    .margin(1)
+    .areas(area);
+
+    // The value stays masked while typing unless values are revealed
+    let masked = "*".repeat(form.value.chars().count());
+    let value_text = match reveal {
+        true => form.value.as_str(),
+        false => masked.as_str(),
+    };
+
+    for (field, text, area) in [
+        (FormField::Key, form.key.as_str(), key),
+        (FormField::Value, value_text, value),
+        (FormField::Note, form.note.as_str(), note),
+    ] {
+        let name = match field {
+            FormField::Key => " Key ",
+            FormField::Value => " Value ",
+            FormField::Note => " Note (Enter adds a line) ",
+        };
+        let active = form.field == field;
+        let mut block = Block::bordered().title(name);
+        if active {
+            block = block.border_style(Style::new().fg(Color::Cyan));
+        }
+        let text = match active {
+            true => format!("{text}█"),
+            false => text.to_string(),
+        };
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+fn draw_help(frame: &mut Frame) {
+    let lines: Vec<Line> = HELP
+        .iter()
+        .map(|(keys, action)| {
+            Line::from(vec![
+                Span::from(format!("{keys:<15}")).bold(),
+                Span::from(*action),
+            ])
+        })
+        .collect();
+
+    let area = popup(frame.area(), 70, HELP.len() as u16 + 2);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::bordered()
+                .title(" Keys ")
+                .title_bottom(" Press any key to close "),
+        ),
+        area,
+    );
+}
+
+/// A centered area of `width` percent of `area`, and `height` rows.
+fn popup(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    let [area] = Layout::horizontal([Constraint::Percentage(width)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    area
+}
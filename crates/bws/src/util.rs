@@ -1,6 +1,17 @@
+use std::{
+    future::Future,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use color_eyre::eyre::{bail, Result};
 use regex::Regex;
 use uuid::Uuid;
 
+use crate::error::{CliError, ErrorCategory};
+
 const VALID_POSIX_NAME_REGEX: &str = "^[a-zA-Z_][a-zA-Z0-9_]*$";
 const STRING_TO_BOOL_ERROR_MESSAGE: &str = "Could not convert string to bool";
 
@@ -26,6 +37,184 @@ pub(crate) fn uuid_to_posix(uuid: &Uuid) -> String {
     format!("_{}", uuid.to_string().replace('-', "_"))
 }
 
+/// Converts a glob, where `*` matches any number of characters and `?` matches a single one, into
+/// a regex matching the whole string.
+pub(crate) fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Splits `value` into words at punctuation, whitespace and lower to upper case changes, and joins
+/// them in lower case with underscores, so `db-password` and `dbPassword` become `db_password`.
+pub(crate) fn to_snake_case(value: &str) -> String {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut after_lower = false;
+
+    for c in value.chars() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            after_lower = false;
+            continue;
+        }
+
+        if c.is_uppercase() && after_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+        after_lower = c.is_lowercase() || c.is_numeric();
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words.join("_")
+}
+
+/// Parses a duration such as `90m`, `12h`, `30d` or `2w`.
+pub(crate) fn parse_duration(value: &str) -> Result<TimeDelta> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_start);
+
+    let Ok(amount) = amount.parse::<i64>() else {
+        bail!("Invalid duration `{value}`, expected a number followed by s, m, h, d or w");
+    };
+
+    let duration = match unit {
+        "s" => TimeDelta::try_seconds(amount),
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => bail!("Invalid duration `{value}`, expected a number followed by s, m, h, d or w"),
+    };
+
+    match duration {
+        Some(duration) => Ok(duration),
+        None => bail!("Duration `{value}` is out of range"),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn create_private_dir(path: &Path) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn create_private_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+pub(crate) fn create_private_file(path: &Path) -> Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn create_private_file(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Writes `contents` to `path`, which is only readable and writable by the current user.
+#[cfg(unix)]
+pub(crate) fn write_private_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    use std::{
+        io::Write,
+        os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_ref())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn write_private_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Milliseconds spent waiting for the user, which don't count towards timeouts.
+static PAUSED_MILLIS: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `f`, which waits for the user, without the time it takes counting towards timeouts.
+pub(crate) fn exclude_from_timeouts<T>(f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let output = f();
+    let millis = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    PAUSED_MILLIS.fetch_add(millis, Ordering::Relaxed);
+    output
+}
+
+/// Runs `future`, failing if it doesn't complete within `timeout`. Time spent in prompts is added
+/// to the timeout, so answering slowly doesn't fail the requests that follow.
+pub(crate) async fn with_timeout<F: Future>(
+    timeout: Option<TimeDelta>,
+    future: F,
+) -> Result<F::Output> {
+    let Some(timeout) = timeout else {
+        return Ok(future.await);
+    };
+
+    let paused_at_start = PAUSED_MILLIS.load(Ordering::Relaxed);
+    let mut deadline = tokio::time::Instant::now() + timeout.to_std()?;
+    let mut extended = 0;
+    tokio::pin!(future);
+
+    loop {
+        match tokio::time::timeout_at(deadline, &mut future).await {
+            Ok(output) => return Ok(output),
+            Err(_) => {
+                let paused = PAUSED_MILLIS.load(Ordering::Relaxed) - paused_at_start;
+                if paused > extended {
+                    deadline += Duration::from_millis(paused - extended);
+                    extended = paused;
+                    continue;
+                }
+                bail!(CliError::new(
+                    ErrorCategory::Network,
+                    format!("The request timed out after {}s", timeout.num_seconds())
+                ));
+            }
+        }
+    }
+}
+
 mod tests {
     #[allow(unused_imports)]
     use super::*;
@@ -90,4 +279,56 @@ mod tests {
         let result = string_to_bool("hello world");
         assert_eq!(result, Err(STRING_TO_BOOL_ERROR_MESSAGE));
     }
+
+    #[tokio::test]
+    async fn test_with_timeout_excludes_prompts() {
+        let output = with_timeout(Some(TimeDelta::milliseconds(50)), async {
+            exclude_from_timeouts(|| std::thread::sleep(Duration::from_millis(100)));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            1
+        })
+        .await;
+        assert_eq!(1, output.unwrap());
+
+        let output = with_timeout(
+            Some(TimeDelta::milliseconds(10)),
+            tokio::time::sleep(Duration::from_millis(100)),
+        )
+        .await;
+        assert!(output.is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(TimeDelta::minutes(90), parse_duration("90m").unwrap());
+        assert_eq!(TimeDelta::hours(12), parse_duration("12h").unwrap());
+        assert_eq!(TimeDelta::days(30), parse_duration("30d").unwrap());
+        assert_eq!(TimeDelta::weeks(2), parse_duration("2w").unwrap());
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("30y").is_err());
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let regex = glob_to_regex("OLD_*.key?").unwrap();
+        assert!(regex.is_match("OLD_DB.key1"));
+        assert!(regex.is_match("OLD_.keyA"));
+        assert!(!regex.is_match("OLD_DBxkey1"));
+        assert!(!regex.is_match("NEW_OLD_DB.key1"));
+    }
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!("db_password", to_snake_case("db-password"));
+        assert_eq!("db_password", to_snake_case("dbPassword"));
+        assert_eq!("db_password", to_snake_case("DB_PASSWORD"));
+        assert_eq!("api2_key", to_snake_case("api2Key"));
+        assert_eq!("smtp_host", to_snake_case(" smtp.host "));
+    }
 }